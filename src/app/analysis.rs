@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use crate::{
+    artifacts::duplicates::{registry_crates, DuplicateCrate, DuplicatesReport},
+    ui::components::{
+        list_with_state::ListWithState,
+        message::{Message, MessageType},
+    },
+    utils::sharable_state::SharableState,
+};
+
+use super::AppState;
+
+/// Cross-project view of the crates compiled redundantly, it replaces the list while opened
+pub struct Analysis {
+    pub report: DuplicatesReport,
+    pub crates: ListWithState<DuplicateCrate>,
+}
+
+impl SharableState<AppState> {
+    /// read the registry crates of every found target, and open the duplicates analysis
+    ///
+    /// it reads and parses files of every target, it runs on its own thread so that the ui keeps drawing the message meanwhile
+    pub fn open_analysis(&self) {
+        let mut paths = vec![];
+        self.mutate(|data| {
+            if data.analysing {
+                return;
+            }
+            paths = data
+                .target_directories
+                .datas
+                .iter()
+                .filter(|t| !t.is_deleted)
+                .map(|t| t.path.clone())
+                .collect();
+            data.analysing = !paths.is_empty();
+            if data.analysing {
+                data.message = Some(Message::new(
+                    "Analysing dependencies of all targets ⏳",
+                    MessageType::Info,
+                    None,
+                    None,
+                ));
+            }
+        });
+        if paths.is_empty() {
+            return;
+        }
+
+        let builds_by_target = paths
+            .iter()
+            .map(|path| registry_crates(Path::new(path)))
+            .collect::<Vec<_>>();
+        let report = DuplicatesReport::new(&builds_by_target);
+
+        self.mutate(|data| {
+            data.analysing = false;
+            data.message = None;
+            data.analysis = Some(Analysis {
+                crates: ListWithState {
+                    index: 0,
+                    datas: report.crates.clone(),
+                },
+                report,
+            })
+        });
+    }
+
+    pub fn close_analysis(&self) {
+        self.mutate(|data| data.analysis = None)
+    }
+
+    pub fn analysis_next(&self) {
+        self.mutate(|data| data.analysis.iter_mut().for_each(|a| a.crates.next()))
+    }
+
+    pub fn analysis_previous(&self) {
+        self.mutate(|data| data.analysis.iter_mut().for_each(|a| a.crates.previous()))
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+
+use crate::{
+    backends::DeleteBackend,
+    config::ConfirmPolicy,
+    ui::components::{
+        message::{Message, MessageType},
+        popup::{Popup, PopupAction},
+    },
+    utils::{
+        bytes_len_to_string_prefix, disk::DiskSpace, parse_bytes, sharable_state::SharableState,
+    },
+};
+
+use super::{
+    predicate::{PromptMode, SelectionPrompt},
+    AppState, TargetDir,
+};
+
+/// How much space the user wants back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    /// free this many bytes, across all the filesystems
+    Free(u64),
+    /// delete until the filesystem of the targets has this percentage of free space
+    UntilFree(f64),
+}
+
+/// Which targets are deleted first to meet a budget
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BudgetPolicy {
+    /// least recently built first
+    #[default]
+    Oldest,
+    /// biggest first, so that the fewest targets are deleted
+    Largest,
+}
+
+/// Targets chosen to meet a budget, in the order they're deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetPlan {
+    pub paths: Vec<String>,
+    pub size: u64,
+    /// false when deleting every candidate isn't enough
+    pub reachable: bool,
+}
+
+/// The space of the filesystems concerned by a budget, measured again as the deletions complete
+#[derive(Debug, Clone)]
+pub struct BudgetProgress {
+    pub budget: Budget,
+    /// a folder on each filesystem, with its space when the budget started, by device id
+    filesystems: HashMap<u64, (PathBuf, DiskSpace)>,
+    /// planned targets, only they stop being deleted once the budget is met
+    pub paths: Vec<String>,
+}
+
+impl Budget {
+    /// e.g: "50G" to free 50 GiB, or "20%" to free space until 20% of the filesystem is free
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().strip_suffix('%') {
+            Some(percent) => Ok(Budget::UntilFree(parse_percent(percent)?)),
+            None => Ok(Budget::Free(parse_bytes(input)?)),
+        }
+    }
+
+    /// e.g: "free 50.0 GiB" or "until 20% is free"
+    pub fn describe(&self) -> String {
+        match self {
+            Budget::Free(bytes) => format!("free {}", bytes_len_to_string_prefix(*bytes)),
+            Budget::UntilFree(percent) => format!("until {percent}% is free"),
+        }
+    }
+}
+
+impl BudgetPolicy {
+    /// the policy after this one, `Tab` cycles through them in the prompt
+    pub fn next(&self) -> Self {
+        match self {
+            BudgetPolicy::Oldest => BudgetPolicy::Largest,
+            BudgetPolicy::Largest => BudgetPolicy::Oldest,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            BudgetPolicy::Oldest => "least recently built first",
+            BudgetPolicy::Largest => "largest first",
+        }
+    }
+}
+
+/// the percentage of a budget, between 0 and 100
+pub fn parse_percent(percent: &str) -> Result<f64> {
+    match percent.trim().trim_end_matches('%').parse::<f64>() {
+        Ok(percent) if percent > 0.0 && percent < 100.0 => Ok(percent),
+        _ => Err(anyhow!(
+            "'{percent}' isn't a percentage of free space, e.g: 20%"
+        )),
+    }
+}
+
+/// the trash and the quarantine only move the targets, they can't free anything
+pub fn check_backend(backend: &DeleteBackend) -> Result<()> {
+    match backend {
+        DeleteBackend::Trash | DeleteBackend::Quarantine { .. } => Err(anyhow!(
+            "the {} backend doesn't free any space, a budget needs remove or cargo clean",
+            backend.name()
+        )),
+        DeleteBackend::Remove | DeleteBackend::CargoClean(_) => Ok(()),
+    }
+}
+
+/// choose the targets to delete to meet the budget, `space_of` tells the space of the filesystem holding a path
+///
+/// the targets already deleted or in the deletion queue aren't candidates
+pub fn plan_budget(
+    budget: Budget,
+    policy: BudgetPolicy,
+    targets: &[TargetDir],
+    space_of: impl Fn(&Path) -> Option<DiskSpace>,
+) -> BudgetPlan {
+    let mut candidates = targets
+        .iter()
+        .filter(|t| !t.is_deleted && !t.is_pinned && t.deletion.is_none())
+        .collect::<Vec<_>>();
+    match policy {
+        BudgetPolicy::Oldest => candidates.sort_by_key(|t| t.last_modified),
+        BudgetPolicy::Largest => candidates.sort_by_key(|t| std::cmp::Reverse(t.size_bytes)),
+    }
+
+    let mut plan = BudgetPlan::default();
+    match budget {
+        Budget::Free(bytes) => {
+            for target in candidates {
+                if plan.size >= bytes {
+                    break;
+                }
+                plan.paths.push(target.path.clone());
+                plan.size += target.size_bytes;
+            }
+            plan.reachable = plan.size >= bytes;
+        }
+        Budget::UntilFree(percent) => {
+            // bytes still missing on each filesystem
+            let mut missing = HashMap::<u64, u64>::new();
+            for target in candidates {
+                let Some(space) = space_of(parent(&target.path)) else {
+                    continue;
+                };
+                let missing = missing.entry(space.device).or_insert_with(|| {
+                    ((space.total as f64 * percent / 100.0) as u64).saturating_sub(space.free)
+                });
+                if *missing == 0 {
+                    continue;
+                }
+                *missing = missing.saturating_sub(target.size_bytes);
+                plan.paths.push(target.path.clone());
+                plan.size += target.size_bytes;
+            }
+            plan.reachable = missing.values().all(|missing| *missing == 0);
+        }
+    }
+    plan
+}
+
+impl BudgetProgress {
+    /// remember the space of the filesystems holding the planned targets before they're deleted
+    pub fn new(budget: Budget, paths: Vec<String>) -> Self {
+        let mut filesystems = HashMap::new();
+        for path in &paths {
+            let folder = parent(path);
+            if let Ok(space) = DiskSpace::of(folder) {
+                filesystems
+                    .entry(space.device)
+                    .or_insert((folder.to_path_buf(), space));
+            }
+        }
+        Self {
+            budget,
+            filesystems,
+            paths,
+        }
+    }
+
+    /// the space of each filesystem now, next to its space when the budget started
+    fn spaces(&self) -> Vec<(DiskSpace, DiskSpace)> {
+        self.filesystems
+            .values()
+            .filter_map(|(folder, start)| Some((*start, DiskSpace::of(folder).ok()?)))
+            .collect()
+    }
+
+    /// bytes the filesystems gained since the budget started
+    pub fn gained(&self) -> u64 {
+        self.spaces()
+            .iter()
+            .map(|(start, now)| now.free.saturating_sub(start.free))
+            .sum()
+    }
+
+    /// whether deleting this planned target still helps, it's asked right before its deletion
+    pub fn is_needed(&self, path: &str) -> bool {
+        match self.budget {
+            Budget::Free(bytes) => self.gained() < bytes,
+            Budget::UntilFree(percent) => DiskSpace::of(parent(path))
+                .map(|space| (space.free as f64) < space.total as f64 * percent / 100.0)
+                .unwrap_or(true),
+        }
+    }
+
+    pub fn is_reached(&self) -> bool {
+        match self.budget {
+            Budget::Free(bytes) => self.gained() >= bytes,
+            Budget::UntilFree(percent) => self
+                .spaces()
+                .iter()
+                .all(|(_, now)| now.free as f64 >= now.total as f64 * percent / 100.0),
+        }
+    }
+
+    /// e.g: "12.0 GiB of 50.0 GiB freed" or "18.2% free, until 20%"
+    pub fn status(&self) -> String {
+        match self.budget {
+            Budget::Free(bytes) => format!(
+                "{} of {} freed",
+                bytes_len_to_string_prefix(self.gained()),
+                bytes_len_to_string_prefix(bytes)
+            ),
+            Budget::UntilFree(percent) => {
+                let (free, total) = self
+                    .spaces()
+                    .iter()
+                    .fold((0, 0), |(free, total), (_, now)| {
+                        (free + now.free, total + now.total)
+                    });
+                format!(
+                    "{:.1}% free, until {percent}%",
+                    free as f64 * 100.0 / total.max(1) as f64
+                )
+            }
+        }
+    }
+}
+
+impl AppState {
+    /// the targets to delete to meet the budget typed in the prompt
+    pub(super) fn budget_plan(
+        &self,
+        input: &str,
+        policy: BudgetPolicy,
+    ) -> Result<(Budget, BudgetPlan)> {
+        let budget = Budget::parse(input)?;
+        check_backend(&self.backend)?;
+        let deletable = self
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| !t.is_protected(self.ignore_protection))
+            .cloned()
+            .collect::<Vec<_>>();
+        let plan = plan_budget(budget, policy, &deletable, |path| DiskSpace::of(path).ok());
+        Ok((budget, plan))
+    }
+}
+
+impl SharableState<AppState> {
+    /// open the selection prompt asking how much space to free
+    pub fn open_budget_prompt(&self) {
+        self.mutate(|data| {
+            data.selection_prompt = Some(SelectionPrompt {
+                mode: PromptMode::Budget(BudgetPolicy::default()),
+                ..Default::default()
+            });
+            data.update_prompt_preview();
+        })
+    }
+
+    /// switch the policy choosing the targets of the budget
+    pub fn selection_prompt_next_policy(&self) {
+        self.mutate(|data| {
+            if let Some(prompt) = &mut data.selection_prompt {
+                if let PromptMode::Budget(policy) = prompt.mode {
+                    prompt.mode = PromptMode::Budget(policy.next());
+                }
+            }
+            data.update_prompt_preview();
+        })
+    }
+
+    /// mark only the targets planned to meet the budget, and ask to confirm their deletion unless the config says not to
+    pub(super) fn apply_budget_prompt(&self, policy: BudgetPolicy) {
+        let (budget, plan, planned, confirm) = {
+            let state = self.read();
+            let Some(prompt) = &state.selection_prompt else {
+                return;
+            };
+            let Ok((budget, plan)) = state.budget_plan(&prompt.input, policy) else {
+                return;
+            };
+            // in the order of the plan, the deletion stops once the budget is met
+            let planned = plan
+                .paths
+                .iter()
+                .filter_map(|path| {
+                    state
+                        .target_directories
+                        .datas
+                        .iter()
+                        .find(|t| &t.path == path)
+                        .cloned()
+                })
+                .collect::<Vec<_>>();
+            (budget, plan, planned, state.confirm)
+        };
+        if plan.paths.is_empty() {
+            self.close_selection_prompt();
+            return self.set_message(Some(Message::new(
+                &format!(
+                    "Nothing to delete, there's already enough free space to {}",
+                    budget.describe()
+                ),
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            )));
+        }
+
+        self.mutate(|data| {
+            for target in data.target_directories.datas.iter_mut() {
+                target.is_marked = plan.paths.contains(&target.path);
+            }
+            data.selection_prompt = None;
+        });
+        if confirm == ConfirmPolicy::Never {
+            return self.start_budget(budget, plan.paths);
+        }
+        let mut lines = vec![format!("To {}, {}:", budget.describe(), policy.describe())];
+        lines.extend(self.deletion_summary(&planned));
+        if !plan.reachable {
+            lines.push("Deleting all of them isn't enough to meet it".to_string());
+        }
+        self.set_popup(Some(Popup::new(
+            "Free space",
+            lines,
+            PopupAction::FreeSpace(budget, plan.paths),
+        )));
+    }
+
+    /// queue the planned deletions, the space of their filesystems is measured from now on
+    pub fn start_budget(&self, budget: Budget, paths: Vec<String>) {
+        let progress = BudgetProgress::new(budget, paths.clone());
+        self.mutate(|data| data.budget = Some(progress));
+        self.enqueue_deletions(paths);
+    }
+}
+
+/// the folder holding the target, it stays on the disk once the target is deleted
+fn parent(path: &str) -> &Path {
+    Path::new(path).parent().unwrap_or(Path::new("/"))
+}
+
+#[cfg(test)]
+mod budget_tests {
+    use std::path::Path;
+
+    use chrono::{Duration, Local};
+
+    use crate::{app::TargetDir, utils::disk::DiskSpace};
+
+    use super::{plan_budget, Budget, BudgetPolicy};
+
+    fn target(path: &str, size_bytes: u64, days: i64) -> TargetDir {
+        TargetDir {
+            path: path.to_string(),
+            size_bytes,
+            last_modified: Local::now() - Duration::days(days),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_budget() {
+        assert_eq!(Budget::parse("2K").unwrap(), Budget::Free(2048));
+        assert_eq!(Budget::parse("20%").unwrap(), Budget::UntilFree(20.0));
+        assert!(Budget::parse("120%").is_err());
+        assert!(Budget::parse("lots").is_err());
+    }
+
+    #[test]
+    fn test_plan_budget() {
+        let targets = vec![
+            target("/a/new/target", 300, 1),
+            target("/a/old/target", 100, 90),
+            target("/a/mid/target", 200, 30),
+            target("/b/old/target", 500, 60),
+        ];
+        // /a has 10% free, /b 50%
+        let space_of = |path: &Path| {
+            Some(match path.starts_with("/a") {
+                true => DiskSpace {
+                    device: 1,
+                    free: 100,
+                    total: 1000,
+                },
+                false => DiskSpace {
+                    device: 2,
+                    free: 500,
+                    total: 1000,
+                },
+            })
+        };
+
+        let plan = plan_budget(Budget::Free(250), BudgetPolicy::Oldest, &targets, space_of);
+        assert_eq!(plan.paths, ["/a/old/target", "/b/old/target"]);
+        assert!(plan.reachable);
+
+        let plan = plan_budget(Budget::Free(250), BudgetPolicy::Largest, &targets, space_of);
+        assert_eq!(plan.paths, ["/b/old/target"]);
+
+        // only /a misses space, 150 bytes to reach 25%
+        let plan = plan_budget(
+            Budget::UntilFree(25.0),
+            BudgetPolicy::Oldest,
+            &targets,
+            space_of,
+        );
+        assert_eq!(plan.paths, ["/a/old/target", "/a/mid/target"]);
+        assert_eq!(plan.size, 300);
+        assert!(plan.reachable);
+
+        let plan = plan_budget(Budget::Free(5000), BudgetPolicy::Oldest, &targets, space_of);
+        assert_eq!(plan.paths.len(), 4);
+        assert!(!plan.reachable);
+
+        // the pinned targets are never planned
+        let mut targets = targets;
+        targets[3].is_pinned = true;
+        let plan = plan_budget(Budget::Free(250), BudgetPolicy::Largest, &targets, space_of);
+        assert_eq!(plan.paths, ["/a/new/target"]);
+    }
+}
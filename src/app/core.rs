@@ -1,36 +1,66 @@
 use std::{
+    collections::HashMap,
     fs,
+    path::{Path, PathBuf},
     sync::mpsc::{self, Sender},
     thread,
     time::Duration,
 };
 
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local};
 use toml::Table;
 
 use crate::{
-    ui::components::message::{Message, MessageType},
-    utils::{bytes_len_to_string_prefix, sharable_state::SharableState},
+    artifacts::{
+        dedupe::{DedupePlan, LinkKind},
+        prune::{PruneOutcome, PrunePlan, DEFAULT_MAX_AGE_DAYS},
+        toolchains::INSTALLED_RUSTC,
+    },
+    audit::{AuditAction, AuditRecord},
+    config::{
+        self,
+        project::{ProjectMetadata, Protection},
+    },
+    ui::components::{
+        message::{Message, MessageType},
+        popup::{Popup, PopupAction},
+    },
+    utils::{bytes_len_to_string_prefix, dry_run, sharable_state::SharableState},
 };
 
-use super::{AppState, TargetDir};
+use super::{pins::Pins, predicate::matches_all, AppState, TargetDir, TargetKind};
 
 #[derive(Debug)]
 enum TraverseMsg {
-    Data((TargetDir, u64)),
+    Data(TargetDir),
     Exit,
 }
 
 impl SharableState<AppState> {
-    /// add item to target dirs
+    /// add item to target dirs, a pinned one at the end of the pinned section
     pub fn push_to_list(&self, target: TargetDir) {
-        self.mutate(|data| data.target_directories.datas.push(target));
+        self.mutate(|data| {
+            let list = &mut data.target_directories;
+            match target.is_pinned {
+                true => {
+                    let at = list.pinned_count();
+                    if !list.datas.is_empty() && list.index >= at {
+                        list.index += 1;
+                    }
+                    list.datas.insert(at, target);
+                }
+                false => list.datas.push(target),
+            }
+        });
     }
 
-    /// clear all items from target dirs
+    /// clear all items from target dirs, and the explored trees since they're about to be outdated
     pub fn clear_list(&self) {
-        self.mutate(|data| data.target_directories.datas.clear());
+        self.mutate(|data| {
+            data.target_directories.datas.clear();
+            data.explorer_cache.clear();
+        });
     }
 
     pub fn set_searching(&self, searching: bool) {
@@ -47,77 +77,417 @@ impl SharableState<AppState> {
         self.mutate(|data| data.target_directories.next())
     }
 
-    /// Will get the currently selected item, and call the `.delete()` method on it, deleting permanently the underlying folder
+    /// queue the deletion of the currently selected item, without asking for confirmation
     pub fn delete_current_item(&self) {
-        let (sender, receiver) = mpsc::channel::<bool>();
-        self.mutate(|data| {
-            let current_idx = data.target_directories.index;
-            let is_deleted = data.target_directories.datas[current_idx].delete().is_ok();
-            let _ = sender.send(is_deleted);
-        });
+        if let Some(protection) = self.current_protection() {
+            return self.set_message(Some(protection));
+        }
+        let path = {
+            let state = self.read();
+            match state.target_directories.datas.is_empty() {
+                true => return,
+                false => state.target_directories.current().path.clone(),
+            }
+        };
+        self.enqueue_deletions(vec![path]);
+    }
 
-        match receiver.recv_timeout(Duration::from_millis(500)) {
-            Ok(is_ok) => match is_ok {
-                true => self.set_message(Some(Message::new(
-                    "Successfully deleted folder",
-                    MessageType::Success,
-                    Some(Duration::from_secs(3)),
-                    None,
-                ))),
-                false => self.set_message(Some(Message::new(
-                    "Failed to delete folder, try again...",
-                    MessageType::Error,
-                    None,
+    /// set a new ui message, as the name imply it overwrites the previous message (if there is some)
+    pub fn set_message(&self, message: Option<Message>) {
+        self.mutate(|data| data.message = message)
+    }
+
+    /// set a new confirmation popup, or close the current one with `None`
+    pub fn set_popup(&self, popup: Option<Popup>) {
+        self.mutate(|data| data.popup = popup)
+    }
+
+    /// close the current popup and run the action the user just confirmed
+    pub fn confirm_popup(&self) {
+        let mut popup = None;
+        self.mutate(|data| popup = data.popup.take());
+
+        match popup.map(|p| p.action) {
+            Some(PopupAction::Prune(plan)) => self.prune(plan),
+            Some(PopupAction::Dedupe { plan, link, .. }) => self.dedupe(plan, link),
+            Some(PopupAction::DeleteTargets(paths)) => self.enqueue_deletions(paths),
+            Some(PopupAction::FreeSpace(budget, paths)) => self.start_budget(budget, paths),
+            Some(PopupAction::Unpin(manifest_path)) => self.set_pinned(&manifest_path, false),
+            Some(PopupAction::DeleteExplorerSelection(path)) => {
+                self.delete_explorer_selection(path)
+            }
+            None => (),
+        }
+    }
+
+    /// look for the artifacts of the currently selected target that weren't used for a while, and ask the user to confirm their removal
+    pub fn prune_current_item(&self) {
+        let target = {
+            let state = self.read();
+            match state.target_directories.datas.is_empty() {
+                true => return,
+                false => state.target_directories.current().clone(),
+            }
+        };
+        if target.is_deleted {
+            return;
+        }
+        let Some(keep) = self.kept_folders(&target.path) else {
+            return;
+        };
+
+        self.set_message(Some(Message::new(
+            "Looking for stale artifacts ⏳",
+            MessageType::Info,
+            None,
+            None,
+        )));
+        let plan = PrunePlan::stale_units(
+            Path::new(&target.path),
+            Duration::from_secs(DEFAULT_MAX_AGE_DAYS * 24 * 3600),
+        )
+        .without_kept(&keep);
+        self.set_message(None);
+
+        if plan.is_empty() {
+            self.set_message(Some(Message::new(
+                &format!("Nothing unused for more than {DEFAULT_MAX_AGE_DAYS} days in this target"),
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            )));
+            return;
+        }
+
+        self.set_popup(Some(Popup::new(
+            "Prune stale artifacts",
+            vec![
+                format!("{}: {}", target.project_name, target.path),
+                format!(
+                    "{} units weren't built for more than {DEFAULT_MAX_AGE_DAYS} days",
+                    plan.units.len()
+                ),
+                format!(
+                    "Pruning them would free {}",
+                    bytes_len_to_string_prefix(plan.size())
+                ),
+            ],
+            PopupAction::Prune(plan),
+        )));
+    }
+
+    /// look for the artifacts of the currently selected target that were built by compilers that aren't installed anymore, and ask the user to confirm their removal
+    pub fn prune_outdated_toolchains_current_item(&self) {
+        let target = {
+            let state = self.read();
+            match state.target_directories.datas.is_empty() {
+                true => return,
+                false => state.target_directories.current().clone(),
+            }
+        };
+        if target.is_deleted {
+            return;
+        }
+        let Some(keep) = self.kept_folders(&target.path) else {
+            return;
+        };
+
+        let plan = PrunePlan::outdated_toolchain_units(Path::new(&target.path), &INSTALLED_RUSTC)
+            .without_kept(&keep);
+        if plan.is_empty() {
+            self.set_message(Some(Message::new(
+                "Everything in this target was built by a rustc you still have",
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            )));
+            return;
+        }
+
+        let mut lines = vec![
+            format!("{}: {}", target.project_name, target.path),
+            format!(
+                "{} built by rustc versions you no longer have:",
+                bytes_len_to_string_prefix(plan.size())
+            ),
+        ];
+        lines.extend(plan.rustc_versions());
+        self.set_popup(Some(Popup::new(
+            "Prune outdated toolchains artifacts",
+            lines,
+            PopupAction::Prune(plan),
+        )));
+    }
+
+    /// the folders of the target its Cargo.toml keeps, none if it can't be pruned at all, the user is told why
+    fn kept_folders(&self, path: &str) -> Option<Vec<String>> {
+        if let Some(protection) = self.target_protection(path) {
+            self.set_message(Some(protection));
+            return None;
+        }
+        match self.verify_target(path) {
+            Ok(metadata) => Some(metadata.keep),
+            Err(e) => {
+                self.set_message(Some(Message::new(
+                    &format!("Can't prune it, {e}"),
+                    MessageType::Warning,
+                    Some(Duration::from_secs(4)),
                     None,
-                ))),
-            },
-            Err(_) => self.set_message(Some(Message::new(
-                "Failed to delete folder, try again...",
+                )));
+                None
+            }
+        }
+    }
+
+    /// removes the units of the plan, and refresh the sizes of the pruned target
+    fn prune(&self, plan: PrunePlan) {
+        let path = plan.target.to_str().unwrap_or_default().to_string();
+        // it may have been pinned, or its Cargo.toml changed, since the plan was confirmed
+        let Some(keep) = self.kept_folders(&path) else {
+            return;
+        };
+        let plan = plan.without_kept(&keep);
+        let project = self
+            .read()
+            .target_directories
+            .datas
+            .iter()
+            .find(|t| t.path == path)
+            .map(|t| t.project_name.clone())
+            .unwrap_or_default();
+        let record = AuditRecord::new(AuditAction::Prune, &path, &project, "remove");
+        let PruneOutcome { freed, failures } = plan.execute();
+        self.audit(match failures.is_empty() {
+            true => record.freed(freed),
+            false => record.freed(freed).failed(
+                format!(
+                    "{} files couldn't be removed: {}",
+                    failures.len(),
+                    failures.join(", ")
+                ),
+                freed > 0,
+            ),
+        });
+        let new_size = fs_extra::dir::get_size(&path).unwrap_or(0);
+        let outdated_size =
+            PrunePlan::outdated_toolchain_units(&plan.target, &INSTALLED_RUSTC).size();
+        self.mutate(|data| {
+            if let Some(target) = data
+                .target_directories
+                .datas
+                .iter_mut()
+                .find(|t| t.path == path)
+            {
+                target.size_bytes = new_size;
+                target.outdated_toolchains_size = outdated_size;
+            }
+            data.explorer_cache.remove(&path);
+            data.update_total_size();
+        });
+        self.record_reclaimed(
+            format!("{} units pruned from {path}", plan.units.len()),
+            freed,
+        );
+        match failures.first() {
+            None => self.set_message(Some(Message::new(
+                &match dry_run::is_enabled() {
+                    true => format!(
+                        "Dry run: {} would be pruned",
+                        bytes_len_to_string_prefix(freed)
+                    ),
+                    false => format!("Pruned {}", bytes_len_to_string_prefix(freed)),
+                },
+                MessageType::Success,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            Some(failure) => self.set_message(Some(Message::new(
+                &format!(
+                    "Pruned {}, {} files couldn't be removed, e.g: {failure}",
+                    bytes_len_to_string_prefix(freed),
+                    failures.len()
+                ),
                 MessageType::Error,
                 None,
                 None,
             ))),
+        }
+    }
+
+    /// look for identical artifacts across the marked targets, or in the selected one if none is, and ask the user to confirm their replacement by reflinks or hardlinks
+    pub fn dedupe_targets(&self) {
+        let (marked, current) = {
+            let state = self.read();
+            let marked = state
+                .target_directories
+                .datas
+                .iter()
+                .filter(|t| {
+                    t.is_marked && !t.is_deleted && !t.is_protected(state.ignore_protection)
+                })
+                .map(|t| PathBuf::from(&t.path))
+                .collect::<Vec<_>>();
+            let current = state
+                .target_directories
+                .datas
+                .get(state.target_directories.index)
+                .filter(|t| !t.is_deleted)
+                .map(|t| t.path.clone());
+            (marked, current)
         };
+        let targets = match (marked.is_empty(), current) {
+            (false, _) => marked,
+            (true, Some(current)) => {
+                if let Some(protection) = self.target_protection(&current) {
+                    return self.set_message(Some(protection));
+                }
+                vec![PathBuf::from(current)]
+            }
+            (true, None) => return,
+        };
+
+        self.set_message(Some(Message::new(
+            "Looking for identical artifacts ⏳",
+            MessageType::Info,
+            None,
+            None,
+        )));
+        let plan = DedupePlan::new(&targets);
+        self.set_message(None);
+
+        if plan.is_empty() {
+            self.set_message(Some(Message::new(
+                match targets.len() {
+                    1 => "No identical artifacts in this target, mark several targets to dedupe them together",
+                    _ => "No identical artifacts across the marked targets",
+                },
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            )));
+            return;
+        }
+
+        self.set_popup(Some(dedupe_popup(plan, targets.len(), LinkKind::Reflink)));
     }
 
-    /// set a new ui message, as the name imply it overwrites the previous message (if there is some)
-    pub fn set_message(&self, message: Option<Message>) {
-        self.mutate(|data| data.message = message)
+    /// switch the dedupe popup between reflinks and hardlinks
+    pub fn toggle_dedupe_link(&self) {
+        self.mutate(|data| {
+            if !matches!(
+                data.popup,
+                Some(Popup {
+                    action: PopupAction::Dedupe { .. },
+                    ..
+                })
+            ) {
+                return;
+            }
+            if let Some(Popup {
+                action:
+                    PopupAction::Dedupe {
+                        plan,
+                        targets,
+                        link,
+                    },
+                ..
+            }) = data.popup.take()
+            {
+                data.popup = Some(dedupe_popup(plan, targets, link.other()));
+            }
+        })
     }
 
-    pub fn set_total_size(&self, val: String) {
+    /// replace the duplicates of the plan by links
+    fn dedupe(&self, plan: DedupePlan, link: LinkKind) {
+        self.set_message(Some(Message::new(
+            "Deduping ⏳",
+            MessageType::Info,
+            None,
+            None,
+        )));
+        let result = plan.execute(link);
+        let root_dirs = self.read().root_dirs.join(" ");
+        let record = AuditRecord::new(AuditAction::Dedupe, &root_dirs, "", link.name());
+        self.audit(match &result {
+            Ok(outcome) if outcome.failures > 0 => record.freed(outcome.freed).failed(
+                format!("{} files couldn't be linked", outcome.failures),
+                true,
+            ),
+            Ok(outcome) => record.freed(outcome.freed),
+            Err(e) => record.failed(e.to_string(), false),
+        });
+        if let Ok(outcome) = &result {
+            self.record_reclaimed(
+                format!(
+                    "{} duplicated files replaced by {}s",
+                    outcome.linked_files,
+                    link.name()
+                ),
+                outcome.freed,
+            );
+        }
+        match result {
+            Ok(outcome) if dry_run::is_enabled() => self.set_message(Some(Message::new(
+                &format!(
+                    "Dry run: {} files would be deduped, {} freed",
+                    outcome.linked_files,
+                    bytes_len_to_string_prefix(outcome.freed)
+                ),
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            Ok(outcome) if outcome.failures == 0 => self.set_message(Some(Message::new(
+                &format!(
+                    "Deduped {} files, {} freed",
+                    outcome.linked_files,
+                    bytes_len_to_string_prefix(outcome.freed)
+                ),
+                MessageType::Success,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            Ok(outcome) => self.set_message(Some(Message::new(
+                &format!(
+                    "Deduped {} files ({} freed), {} couldn't be linked",
+                    outcome.linked_files,
+                    bytes_len_to_string_prefix(outcome.freed),
+                    outcome.failures
+                ),
+                MessageType::Warning,
+                None,
+                None,
+            ))),
+            Err(e) => self.set_message(Some(Message::new(
+                &e.to_string(),
+                MessageType::Error,
+                None,
+                None,
+            ))),
+        }
+    }
+
+    pub fn set_total_size(&self, val: u64) {
         self.mutate(|data| data.total_size = val)
     }
 
     /// will scan the specified directory to find 'target' dirs inside of it, and automatically stream the data in the app state
     pub fn search(&self) {
         self.set_searching(true);
-        let (tx, rx) = mpsc::channel::<TraverseMsg>();
 
-        let path = match &self.read().root_dir {
-            Some(path) => path.to_owned(),
-            None => {
-                self.set_searching(false);
+        let roots = self.read().root_dirs.clone();
+        let filters = self.read().filters.clone();
+        let (mut is_empty, mut total_size) = (true, 0);
+        scan_target_dirs(&roots, |mut target| {
+            if !matches_all(&filters, &mut target) {
                 return;
             }
-        };
-        thread::spawn(move || {
-            find_target_dirs(path, tx.clone());
-            let _ = tx.send(TraverseMsg::Exit);
+            is_empty = false;
+            total_size += target.size_bytes;
+            self.push_to_list(target);
         });
 
-        let (mut is_empty, mut total_size) = (true, 0);
-        for data in rx {
-            match data {
-                TraverseMsg::Data((target, size)) => {
-                    self.push_to_list(target);
-                    is_empty = false;
-                    total_size += size;
-                }
-                TraverseMsg::Exit => break,
-            }
-        }
-
         if is_empty {
             self.set_message(Some(Message::new(
                 "There is no 'target' directories in this scope",
@@ -127,8 +497,119 @@ impl SharableState<AppState> {
             )));
         }
 
-        self.set_total_size(bytes_len_to_string_prefix(total_size));
+        self.set_total_size(total_size);
         self.set_searching(false);
+
+        self.measure_outdated_toolchains();
+        self.measure_git_changes();
+    }
+
+    /// look in every found target for the artifacts built by compilers that aren't installed anymore, it's done after the search since it has to read every unit of every target
+    fn measure_outdated_toolchains(&self) {
+        let paths = self
+            .read()
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| !t.is_deleted)
+            .map(|t| t.path.clone())
+            .collect::<Vec<_>>();
+
+        // every target is read before the state is written, once
+        let sizes = paths
+            .into_iter()
+            .map(|path| {
+                let size =
+                    PrunePlan::outdated_toolchain_units(Path::new(&path), &INSTALLED_RUSTC).size();
+                (path, size)
+            })
+            .filter(|(_, size)| *size > 0)
+            .collect::<HashMap<_, _>>();
+        if sizes.is_empty() {
+            return;
+        }
+        self.mutate(|data| {
+            for target in data.target_directories.datas.iter_mut() {
+                if let Some(size) = sizes.get(&target.path) {
+                    target.outdated_toolchains_size = *size;
+                }
+            }
+        });
+    }
+}
+
+/// the confirmation of a dedupe, `h` in the popup switches to the other kind of links
+fn dedupe_popup(plan: DedupePlan, targets: usize, link: LinkKind) -> Popup {
+    let warning = match link {
+        LinkKind::Reflink => "Projects keep building normally, the files are only shared on disk",
+        LinkKind::Hardlink => {
+            "For filesystems without reflinks, writing one of the linked files changes all of them"
+        }
+    };
+    Popup::new(
+        "Dedupe identical artifacts",
+        vec![
+            format!(
+                "{} files of {targets} targets are copies of another one",
+                plan.duplicates()
+            ),
+            format!(
+                "Replacing them by {}s would free {}",
+                link.name(),
+                bytes_len_to_string_prefix(plan.reclaimable())
+            ),
+            warning.to_string(),
+        ],
+        PopupAction::Dedupe {
+            plan,
+            targets,
+            link,
+        },
+    )
+}
+
+/// search the roots for 'target' dirs, each one is given to `on_found` as soon as it's found
+pub fn scan_target_dirs(roots: &[String], mut on_found: impl FnMut(TargetDir)) {
+    let pins = Pins::load();
+    let (tx, rx) = mpsc::channel::<TraverseMsg>();
+    let roots = roots.to_vec();
+    thread::spawn(move || {
+        for root in roots {
+            find_target_dirs(root, tx.clone());
+        }
+        let _ = tx.send(TraverseMsg::Exit);
+    });
+
+    for data in rx {
+        match data {
+            TraverseMsg::Data(mut target) => {
+                target.is_pinned = pins.contains(Path::new(&target.manifest_path));
+                on_found(target)
+            }
+            TraverseMsg::Exit => break,
+        }
+    }
+}
+
+/// blocking version of the search, returns all the 'target' dirs found under the roots at once
+pub fn collect_target_dirs(roots: &[String]) -> Vec<TargetDir> {
+    let mut found = vec![];
+    scan_target_dirs(roots, |target| found.push(target));
+    found
+}
+
+/// why the target can't be deleted: the config protects its folder, or its Cargo.toml asks to keep it
+fn protection(
+    manifest: &Table,
+    target: &Path,
+    last_modified: DateTime<Local>,
+) -> Option<Protection> {
+    let canonical_target = target.canonicalize().unwrap_or(target.to_path_buf());
+    match config::get().is_protected(&canonical_target) {
+        true => Some(Protection::Config),
+        false => ProjectMetadata::from_manifest(manifest)
+            .protection(last_modified, Local::now())
+            .map(Protection::Manifest),
     }
 }
 
@@ -160,28 +641,33 @@ fn find_target_dirs(path: String, tx: Sender<TraverseMsg>) {
                         .iter()
                         .find(|(key, val)| key == &&"workspace".to_string() && val.is_table());
 
-                    let project_name = match (package, workspace) {
-                        (None, Some(_)) => path
-                            .split('/')
-                            .last()
-                            .ok_or(anyhow!("No path"))?
-                            .to_string(),
+                    let (kind, project_name) = match (package, workspace) {
+                        (None, Some(_)) => (
+                            TargetKind::Workspace,
+                            path.split('/')
+                                .next_back()
+                                .ok_or(anyhow!("No path"))?
+                                .to_string(),
+                        ),
                         (Some(package), None) => {
                             let package_info = package
                                 .1
                                 .as_table()
                                 .ok_or(anyhow!("Cannot parse package"))?;
-                            package_info
-                                .iter()
-                                .find(|(key, val)| key == &&"name".to_string() && val.is_str())
-                                .map(|(_, val)| val.as_str().unwrap().to_string())
-                                .ok_or(anyhow!(""))?
+                            (
+                                TargetKind::Package,
+                                package_info
+                                    .iter()
+                                    .find(|(key, val)| key == &&"name".to_string() && val.is_str())
+                                    .map(|(_, val)| val.as_str().unwrap().to_string())
+                                    .ok_or(anyhow!(""))?,
+                            )
                         }
                         _ => return Err(anyhow!("No package or workspace found")),
                     };
 
                     let metadata = target.metadata()?;
-                    let last_modified: DateTime<Utc> = metadata.modified()?.into();
+                    let last_modified: DateTime<Local> = metadata.modified()?.into();
 
                     let path = target
                         .path()
@@ -189,16 +675,32 @@ fn find_target_dirs(path: String, tx: Sender<TraverseMsg>) {
                         .ok_or(anyhow!("No path"))?
                         .to_string();
                     let folder_size = fs_extra::dir::get_size(&path)?;
-                    let formated_size = bytes_len_to_string_prefix(folder_size);
+                    let manifest_path = cargo_toml
+                        .path()
+                        .to_str()
+                        .ok_or(anyhow!("No path"))?
+                        .to_string();
 
+                    let protection = protection(&toml_values, Path::new(&path), last_modified);
                     let target_dir = TargetDir {
                         path,
                         project_name,
-                        last_modified: last_modified.format("%d/%m/%Y").to_string(),
+                        manifest_path,
+                        kind,
+                        last_modified,
                         is_deleted: false,
-                        size: formated_size,
+                        is_marked: false,
+                        size_bytes: folder_size,
+                        outdated_toolchains_size: 0,
+                        deletion: None,
+                        is_busy: false,
+                        failed_deletion: None,
+                        has_git_changes: None,
+                        protection,
+                        // set by `scan_target_dirs`, which reads the pins once
+                        is_pinned: false,
                     };
-                    let _ = tx.send(TraverseMsg::Data((target_dir, folder_size)));
+                    let _ = tx.send(TraverseMsg::Data(target_dir));
                     Ok(())
                 });
             });
@@ -247,7 +749,7 @@ mod app_tests {
         let mut found = vec![];
         for data in rx {
             match data {
-                TraverseMsg::Data((target, _)) => found.push(target),
+                TraverseMsg::Data(target) => found.push(target),
                 TraverseMsg::Exit => break,
             }
         }
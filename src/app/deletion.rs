@@ -0,0 +1,543 @@
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    artifacts::locks::is_locked,
+    audit::{AuditAction, AuditRecord},
+    backends::{safety::verify_target, DeleteBackend, PartialRemoval, RemovalProgress},
+    config::project::ProjectMetadata,
+    ui::components::{
+        message::{Message, MessageType},
+        popup::{Popup, PopupAction},
+    },
+    utils::{bytes_len_to_string_prefix, disk::DiskSpace, dry_run, sharable_state::SharableState},
+};
+
+use super::{AppState, TargetDir};
+
+/// how often the progress of the deleted target is written to the state
+const PROGRESS_RATE: Duration = Duration::from_millis(100);
+/// number of errors listed in the retry popup, the others are only counted
+const MAX_ERRORS_SHOWN: usize = 8;
+/// how often the locks of the busy targets are probed, to retry their deletion once cargo released them
+pub const LOCK_PROBE_RATE: Duration = Duration::from_secs(2);
+
+/// Target dir waiting in the deletion queue
+pub struct DeletionJob {
+    path: String,
+    backend: DeleteBackend,
+}
+
+/// Where a queued target dir is in its deletion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletionProgress {
+    /// false while it's waiting for the deletions queued before it
+    pub started: bool,
+    /// a cargo process holds the lock of the target, its deletion is retried once it's released
+    pub waiting_for_lock: bool,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// What's known of a target whose deletion failed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailedDeletion {
+    /// bytes removed before it failed
+    pub freed: u64,
+    /// each file left with the reason, or why the deletion couldn't start at all
+    pub errors: Vec<String>,
+}
+
+impl TargetDir {
+    /// e.g: "partially deleted, 312 MiB left, 4 errors"
+    pub fn failure_summary(&self) -> Option<String> {
+        let failure = self.failed_deletion.as_ref().filter(|_| !self.is_deleted)?;
+        let errors = match failure.errors.len() {
+            1 => "1 error".to_string(),
+            n => format!("{n} errors"),
+        };
+        Some(match failure.freed {
+            0 => format!("deletion failed, {errors}"),
+            _ => format!(
+                "partially deleted, {} left, {errors}",
+                bytes_len_to_string_prefix(self.size_bytes)
+            ),
+        })
+    }
+}
+
+/// Result of the deletions ran since the queue was last empty
+#[derive(Default)]
+struct DeletionsOutcome {
+    deleted: usize,
+    freed: u64,
+    failures: Vec<String>,
+    /// targets locked by cargo, their deletion is deferred
+    deferred: usize,
+    /// targets planned for the budget, left on the disk since it was met before their turn
+    skipped: usize,
+}
+
+/// start the thread deleting the queued target dirs one by one, returns the queue
+///
+/// the targets locked by cargo are put aside, and queued again once their lock is released
+pub fn spawn_deletion_worker(state: Arc<SharableState<AppState>>) -> Sender<DeletionJob> {
+    let (sender, receiver) = mpsc::channel::<DeletionJob>();
+    // the state owns the queue, holding on to it would keep both alive once the app is closed
+    let state = Arc::downgrade(&state);
+    thread::spawn(move || {
+        let mut deferred = Vec::<DeletionJob>::new();
+        loop {
+            let mut jobs = match receiver.recv_timeout(LOCK_PROBE_RATE) {
+                Ok(job) => vec![job],
+                Err(RecvTimeoutError::Timeout) => vec![],
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let (released, locked) = deferred
+                .into_iter()
+                .partition(|job: &DeletionJob| !is_locked(Path::new(&job.path)));
+            deferred = locked;
+            jobs.extend(released);
+            if jobs.is_empty() {
+                continue;
+            }
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+
+            let mut outcome = DeletionsOutcome::default();
+            let mut jobs = jobs.into_iter().collect::<VecDeque<_>>();
+            while let Some(job) = jobs.pop_front() {
+                match is_locked(Path::new(&job.path)) {
+                    true => {
+                        state.set_deletion_progress(
+                            &job.path,
+                            Some(DeletionProgress {
+                                waiting_for_lock: true,
+                                ..Default::default()
+                            }),
+                        );
+                        outcome.deferred += 1;
+                        deferred.push(job);
+                    }
+                    false => state.run_deletion(job, &mut outcome),
+                }
+                jobs.extend(receiver.try_iter());
+            }
+            state.report_deletions(outcome);
+        }
+    });
+    sender
+}
+
+/// start the thread probing the locks of the targets every few seconds, so that the ones being built are shown as busy
+///
+/// the state is only written when a target became busy or idle, and the thread stops once the app is closed
+pub fn spawn_lock_watcher(state: Arc<SharableState<AppState>>) {
+    let state = Arc::downgrade(&state);
+    thread::spawn(move || {
+        while let Some(state) = state.upgrade() {
+            let targets = state
+                .read()
+                .target_directories
+                .datas
+                .iter()
+                .filter(|t| !t.is_deleted)
+                .map(|t| (t.path.clone(), t.is_busy))
+                .collect::<Vec<_>>();
+            let changed = targets
+                .into_iter()
+                .filter_map(|(path, was_busy)| {
+                    let busy = is_locked(Path::new(&path));
+                    (busy != was_busy).then_some((path, busy))
+                })
+                .collect::<Vec<_>>();
+            if !changed.is_empty() {
+                state.mutate(|data| {
+                    for (path, busy) in &changed {
+                        if let Some(target) = data
+                            .target_directories
+                            .datas
+                            .iter_mut()
+                            .find(|t| &t.path == path)
+                        {
+                            target.is_busy = *busy;
+                        }
+                    }
+                });
+            }
+            drop(state);
+            thread::sleep(LOCK_PROBE_RATE);
+        }
+    });
+}
+
+impl SharableState<AppState> {
+    /// show why the deletion of the selected target failed, confirming it retries the deletion
+    pub fn ask_retry_deletion(&self) {
+        let target = {
+            let state = self.read();
+            match state
+                .target_directories
+                .datas
+                .get(state.target_directories.index)
+            {
+                Some(target) => target.clone(),
+                None => return,
+            }
+        };
+        let failure = match (&target.failed_deletion, target.is_deleted) {
+            (Some(failure), false) => failure,
+            _ => {
+                return self.set_message(Some(Message::new(
+                    "The deletion of this target didn't fail",
+                    MessageType::Info,
+                    Some(Duration::from_secs(3)),
+                    None,
+                )))
+            }
+        };
+
+        let mut lines = vec![
+            format!("{}: {}", target.project_name, target.path),
+            target.failure_summary().unwrap_or_default(),
+        ];
+        lines.extend(failure.errors.iter().take(MAX_ERRORS_SHOWN).cloned());
+        if failure.errors.len() > MAX_ERRORS_SHOWN {
+            lines.push(format!(
+                "... and {} more",
+                failure.errors.len() - MAX_ERRORS_SHOWN
+            ));
+        }
+        lines.push("Confirm to retry the deletion".to_string());
+        self.set_popup(Some(Popup::new(
+            "Deletion errors",
+            lines,
+            PopupAction::DeleteTargets(vec![target.path]),
+        )));
+    }
+
+    /// queue the deletion of these target dirs with the current backend, the ones already deleted or queued, and the pinned ones, are skipped
+    pub fn enqueue_deletions(&self, paths: Vec<String>) {
+        let (queue, backend) = {
+            let data = self.read();
+            (data.deletion_queue.clone(), data.backend.clone())
+        };
+        let queue = match queue {
+            Some(queue) => queue,
+            None => {
+                return self.set_message(Some(Message::new(
+                    "Deletions aren't available, try again...",
+                    MessageType::Error,
+                    None,
+                    None,
+                )))
+            }
+        };
+
+        self.mutate(|data| {
+            for target in data.target_directories.datas.iter_mut().filter(|t| {
+                paths.contains(&t.path) && !t.is_deleted && !t.is_pinned && t.deletion.is_none()
+            }) {
+                let job = DeletionJob {
+                    path: target.path.clone(),
+                    backend: backend.clone(),
+                };
+                if queue.send(job).is_ok() {
+                    target.deletion = Some(DeletionProgress::default());
+                }
+            }
+        });
+    }
+
+    fn set_deletion_progress(&self, path: &str, progress: Option<DeletionProgress>) {
+        self.mutate(|data| {
+            if let Some(target) = data
+                .target_directories
+                .datas
+                .iter_mut()
+                .find(|t| t.path == path)
+            {
+                target.deletion = progress;
+            }
+        })
+    }
+
+    /// check again that the target can be deleted, it may have changed since the search found it
+    pub(super) fn verify_target(&self, path: &str) -> Result<ProjectMetadata> {
+        let (scan_root, partially_deleted, ignore_protection) = {
+            let data = self.read();
+            let partially_deleted = data
+                .target_directories
+                .datas
+                .iter()
+                .find(|t| t.path == path)
+                .and_then(|t| t.failed_deletion.as_ref())
+                .map(|failure| failure.freed > 0)
+                .unwrap_or(false);
+            let scan_root = data
+                .root_dirs
+                .iter()
+                .find(|root| Path::new(path).starts_with(root))
+                .cloned()
+                .unwrap_or_default();
+            (scan_root, partially_deleted, data.ignore_protection)
+        };
+        verify_target(
+            Path::new(path),
+            Path::new(&scan_root),
+            partially_deleted,
+            ignore_protection,
+        )
+    }
+
+    fn run_deletion(&self, job: DeletionJob, outcome: &mut DeletionsOutcome) {
+        // statvfs tells whether the deletions so far already met the budget
+        let budget_met = self
+            .read()
+            .budget
+            .as_ref()
+            .filter(|budget| budget.paths.contains(&job.path))
+            .map(|budget| !budget.is_needed(&job.path))
+            .unwrap_or(false);
+        if budget_met && !dry_run::is_enabled() {
+            outcome.skipped += 1;
+            return self.mutate(|data| {
+                if let Some(target) = data
+                    .target_directories
+                    .datas
+                    .iter_mut()
+                    .find(|t| t.path == job.path)
+                {
+                    target.deletion = None;
+                    target.is_marked = false;
+                }
+            });
+        }
+
+        let mut progress = DeletionProgress {
+            started: true,
+            ..Default::default()
+        };
+        self.set_deletion_progress(&job.path, Some(progress));
+
+        // the space the disk actually gained, the target's parent stays on the same filesystem
+        let parent = Path::new(&job.path).parent().unwrap_or(Path::new("/"));
+        let space_before = DiskSpace::of(parent);
+        let mut last_update = Instant::now();
+        let result = self.verify_target(&job.path).and_then(|metadata| {
+            job.backend.remove(
+                Path::new(&job.path),
+                &metadata.keep,
+                |removal| match removal {
+                    RemovalProgress::Removed { files, bytes } => {
+                        (progress.files, progress.bytes) = (files, bytes);
+                        if last_update.elapsed() >= PROGRESS_RATE {
+                            self.set_deletion_progress(&job.path, Some(progress));
+                            last_update = Instant::now();
+                        }
+                    }
+                    RemovalProgress::Output(line) => {
+                        self.set_message(Some(Message::new(line, MessageType::Info, None, None)))
+                    }
+                },
+            )
+        });
+        // `cargo clean --release` (or `--doc`, `-p`) and the folders kept by the Cargo.toml leave a part of the target
+        let remaining_size = match Path::new(&job.path).exists() && !dry_run::is_enabled() {
+            true => Some(fs_extra::dir::get_size(&job.path).unwrap_or(0)),
+            false => None,
+        };
+        let space_after = DiskSpace::of(parent);
+
+        let mut audit_record = None;
+        self.mutate(|data| {
+            let mut reclaimed = None;
+            if let Some(target) = data
+                .target_directories
+                .datas
+                .iter_mut()
+                .find(|t| t.path == job.path)
+            {
+                target.deletion = None;
+                let record = AuditRecord::new(
+                    AuditAction::Delete,
+                    &target.path,
+                    &target.project_name,
+                    job.backend.name(),
+                );
+                match (&result, remaining_size) {
+                    (Ok(()), Some(remaining_size)) => {
+                        target.is_marked = false;
+                        target.failed_deletion = None;
+                        outcome.deleted += 1;
+                        outcome.freed += target.size_bytes.saturating_sub(remaining_size);
+                        reclaimed = Some((
+                            format!(
+                                "{}: {} (partly {})",
+                                target.project_name,
+                                target.path,
+                                job.backend.outcome()
+                            ),
+                            target.size_bytes.saturating_sub(remaining_size),
+                        ));
+                        audit_record =
+                            Some(record.freed(target.size_bytes.saturating_sub(remaining_size)));
+                        target.size_bytes = remaining_size;
+                    }
+                    (Ok(()), None) => {
+                        target.is_deleted = true;
+                        target.is_marked = false;
+                        target.failed_deletion = None;
+                        outcome.deleted += 1;
+                        // the trash and the quarantine only rename the folder, nothing is counted while it's moved
+                        outcome.freed += match job.backend {
+                            DeleteBackend::Remove if !dry_run::is_enabled() => progress.bytes,
+                            _ => target.size_bytes,
+                        };
+                        reclaimed = Some((
+                            format!(
+                                "{}: {} ({})",
+                                target.project_name,
+                                target.path,
+                                job.backend.outcome()
+                            ),
+                            match job.backend {
+                                DeleteBackend::Remove => progress.bytes,
+                                DeleteBackend::CargoClean(_) => target.size_bytes,
+                                DeleteBackend::Trash | DeleteBackend::Quarantine { .. } => 0,
+                            },
+                        ));
+                        audit_record = reclaimed.as_ref().map(|(_, freed)| record.freed(*freed));
+                    }
+                    (Err(e), remaining_size) => {
+                        let errors = match e.downcast_ref::<PartialRemoval>() {
+                            Some(partial) => partial.failures.clone(),
+                            None => vec![e.to_string()],
+                        };
+                        outcome.freed += progress.bytes;
+                        outcome.failures.push(format!("{}: {e}", target.path));
+                        if let Some(remaining_size) = remaining_size {
+                            target.size_bytes = remaining_size;
+                        }
+                        if progress.bytes > 0 {
+                            reclaimed = Some((
+                                format!(
+                                    "{}: {} (partially deleted)",
+                                    target.project_name, target.path
+                                ),
+                                progress.bytes,
+                            ));
+                        }
+                        audit_record = Some(
+                            record
+                                .freed(progress.bytes)
+                                .failed(e.to_string(), progress.bytes > 0),
+                        );
+                        target.failed_deletion = Some(FailedDeletion {
+                            freed: progress.bytes,
+                            errors,
+                        });
+                    }
+                }
+            }
+            data.explorer_cache.remove(&job.path);
+
+            if let (Some((description, freed)), false) = (reclaimed, dry_run::is_enabled()) {
+                data.session.record(description, freed);
+                if let (Ok(before), Ok(after)) = (space_before, space_after) {
+                    data.session.track_free_space(parent, before, after);
+                }
+            }
+            data.update_total_size();
+        });
+        if let Some(record) = audit_record {
+            self.audit(record);
+        }
+    }
+
+    /// the failed targets stay marked so that the user can retry
+    fn report_deletions(&self, outcome: DeletionsOutcome) {
+        let DeletionsOutcome {
+            deleted,
+            freed,
+            failures,
+            deferred,
+            skipped,
+        } = outcome;
+        // the budget is over once none of its targets is waiting for a cargo build
+        let mut budget = None;
+        if deferred == 0 {
+            self.mutate(|data| budget = data.budget.take());
+        }
+        let (budget, budget_met) = match budget {
+            Some(budget) if !dry_run::is_enabled() => {
+                let skipped = match skipped {
+                    0 => String::new(),
+                    n => format!(", {n} planned targets kept"),
+                };
+                match budget.is_reached() {
+                    true => (format!(", budget met: {}{skipped}", budget.status()), true),
+                    false => (
+                        format!(", budget not met: {}{skipped}", budget.status()),
+                        false,
+                    ),
+                }
+            }
+            _ => (String::new(), true),
+        };
+        let backend_outcome = self.read().backend.outcome();
+        match failures.first() {
+            None if dry_run::is_enabled() => self.set_message(Some(Message::new(
+                &format!(
+                    "Dry run: {deleted} folders ({}) would be {backend_outcome}",
+                    bytes_len_to_string_prefix(freed)
+                ),
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            None if deferred > 0 => self.set_message(Some(Message::new(
+                &format!(
+                    "Deleted {deleted} folders ({}), {deferred} locked by a running cargo build will be deleted once it's done{budget}",
+                    bytes_len_to_string_prefix(freed)
+                ),
+                MessageType::Warning,
+                Some(Duration::from_secs(5)),
+                None,
+            ))),
+            None => self.set_message(Some(Message::new(
+                &format!(
+                    "Successfully deleted {deleted} folders ({}){budget}",
+                    bytes_len_to_string_prefix(freed)
+                ),
+                match budget_met {
+                    true => MessageType::Success,
+                    false => MessageType::Warning,
+                },
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            Some(first_failure) => self.set_message(Some(Message::new(
+                &format!(
+                    "Deleted {deleted} folders ({}){budget}, {} failed (e on it to see why and retry): {first_failure}",
+                    bytes_len_to_string_prefix(freed),
+                    failures.len()
+                ),
+                MessageType::Error,
+                None,
+                None,
+            ))),
+        }
+    }
+}
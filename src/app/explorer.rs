@@ -0,0 +1,576 @@
+use std::{
+    cmp::Reverse,
+    fs, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    artifacts::deps::{dependencies_usage, ArtifactEntry, DependencyUsage},
+    audit::{AuditAction, AuditRecord},
+    backends::{DeleteBackend, PartialRemoval, RemovalProgress},
+    ui::components::{
+        list_with_state::ListWithState,
+        message::{Message, MessageType},
+        popup::{Popup, PopupAction},
+    },
+    utils::{bytes_len_to_string_prefix, dry_run, sharable_state::SharableState},
+};
+
+use super::AppState;
+
+/// Describes a file or a folder and the size of everything under it, computed once when scanned
+#[derive(Debug, Clone)]
+pub struct DirTree {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+    /// sorted by size, biggest first
+    pub children: Vec<DirTree>,
+}
+
+impl DirTree {
+    /// recursively scan `path`, symlinks aren't followed
+    pub fn scan(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+        let metadata = fs::symlink_metadata(path).ok();
+
+        let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+        let mut children = match is_dir {
+            true => fs::read_dir(path)
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .map(|e| Self::scan(&e.path()))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default(),
+            false => vec![],
+        };
+        children.sort_by_key(|c| Reverse(c.size));
+
+        let own_size = metadata.map(|m| m.len()).unwrap_or(0);
+        let size = match is_dir {
+            true => children.iter().map(|c| c.size).sum(),
+            false => own_size,
+        };
+
+        Self {
+            name,
+            path: path.to_path_buf(),
+            size,
+            is_dir,
+            children,
+        }
+    }
+
+    /// get the node at the end of a path of children indexes
+    pub fn node(&self, indexes: &[usize]) -> Option<&DirTree> {
+        indexes
+            .iter()
+            .try_fold(self, |node, &i| node.children.get(i))
+    }
+
+    /// the profile dirs of a target tree (`debug`, `x86_64-unknown-linux-gnu/release`...), i.e: the dirs holding a `.fingerprint` folder
+    pub fn profiles(&self) -> Vec<&DirTree> {
+        let is_profile = |node: &DirTree| node.children.iter().any(|c| c.name == ".fingerprint");
+        self.children
+            .iter()
+            .flat_map(|child| match is_profile(child) {
+                true => vec![child],
+                false => child.children.iter().filter(|c| is_profile(c)).collect(),
+            })
+            .collect()
+    }
+
+    /// the children indexes leading to the node of this path
+    pub fn indexes_of(&self, path: &Path) -> Option<Vec<usize>> {
+        let (index, child) = self
+            .children
+            .iter()
+            .enumerate()
+            .find(|(_, child)| path.starts_with(&child.path))?;
+        let mut indexes = vec![index];
+        if child.path != path {
+            indexes.extend(child.indexes_of(path)?);
+        }
+        Some(indexes)
+    }
+
+    /// replaces the node at the end of a path of children indexes, or removes it if there's no new one, and updates the size of all its parents
+    pub fn replace(&mut self, indexes: &[usize], new: Option<DirTree>) -> Option<DirTree> {
+        let (last, parents) = indexes.split_last()?;
+        let mut node = self;
+        let old_size = node.node(indexes)?.size;
+        let new_size = new.as_ref().map(|n| n.size).unwrap_or(0);
+        for &i in parents {
+            node.size = node.size + new_size - old_size;
+            node = node.children.get_mut(i)?;
+        }
+        node.size = node.size + new_size - old_size;
+        match new {
+            Some(new) => Some(mem::replace(&mut node.children[*last], new)),
+            None => Some(node.children.remove(*last)),
+        }
+    }
+}
+
+/// ncdu-like navigation state inside a target dir
+pub struct Explorer {
+    /// path of the explored target dir, as found by the search
+    pub target_path: String,
+    pub tree: DirTree,
+    /// children indexes from the root to the currently opened folder
+    pub opened: Vec<usize>,
+    /// selected child in each opened folder, `selected[opened.len()]` is the one of the current folder
+    selected: Vec<usize>,
+    /// when shown, the space used by each dependency of the target replaces the folder view
+    pub dependencies: Option<ListWithState<DependencyUsage>>,
+}
+
+impl Explorer {
+    pub fn new(target_path: String, tree: DirTree) -> Self {
+        Self {
+            target_path,
+            tree,
+            opened: vec![],
+            selected: vec![0],
+            dependencies: None,
+        }
+    }
+
+    /// currently opened folder
+    pub fn current_dir(&self) -> &DirTree {
+        self.tree.node(&self.opened).unwrap_or(&self.tree)
+    }
+
+    /// index of the selected child in the current folder
+    pub fn index(&self) -> usize {
+        *self.selected.last().unwrap_or(&0)
+    }
+
+    pub fn selected(&self) -> Option<&DirTree> {
+        self.current_dir().children.get(self.index())
+    }
+
+    pub fn next(&mut self) {
+        if let Some(dependencies) = self.dependencies.as_mut() {
+            return dependencies.next();
+        }
+        let len = self.current_dir().children.len();
+        if let Some(index) = self.selected.last_mut() {
+            *index = match *index + 1 >= len {
+                true => 0,
+                false => *index + 1,
+            }
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(dependencies) = self.dependencies.as_mut() {
+            return dependencies.previous();
+        }
+        let len = self.current_dir().children.len();
+        if let Some(index) = self.selected.last_mut() {
+            *index = match *index == 0 {
+                true => len.saturating_sub(1),
+                false => *index - 1,
+            }
+        }
+    }
+
+    /// switch between the folder view and the dependencies view, the dependencies are aggregated from the `deps/` and `build/` folders of every profile
+    pub fn toggle_dependencies(&mut self) {
+        if self.dependencies.take().is_some() {
+            return;
+        }
+
+        let mut entries = vec![];
+        for profile in self.tree.profiles() {
+            for (folder, is_build) in [("deps", false), ("build", true)] {
+                if let Some(dir) = profile.children.iter().find(|c| c.name == folder) {
+                    entries.extend(dir.children.iter().map(|c| ArtifactEntry {
+                        file_name: &c.name,
+                        size: c.size,
+                        is_build,
+                    }));
+                }
+            }
+        }
+        self.dependencies = Some(ListWithState {
+            index: 0,
+            datas: dependencies_usage(entries),
+        });
+    }
+
+    /// open the selected child if it's a folder
+    pub fn enter(&mut self) {
+        if self.dependencies.is_some() {
+            return;
+        }
+        if self.selected().map(|s| s.is_dir).unwrap_or(false) {
+            self.opened.push(self.index());
+            self.selected.push(0);
+        }
+    }
+
+    /// go back to the parent folder (or to the folder view), returns false if already at the root of the target
+    pub fn leave(&mut self) -> bool {
+        if self.dependencies.take().is_some() {
+            return true;
+        }
+        if self.opened.pop().is_none() {
+            return false;
+        }
+        self.selected.pop();
+        true
+    }
+
+    /// puts in the tree what's left on the disk of a deleted file or folder, `None` once it's gone
+    ///
+    /// the selection stays inside the opened folder
+    pub fn refresh(&mut self, path: &Path, left: Option<DirTree>) {
+        let indexes = match self.tree.indexes_of(path) {
+            Some(indexes) => indexes,
+            None => return,
+        };
+        self.tree.replace(&indexes, left);
+
+        let len = self.current_dir().children.len();
+        if let Some(index) = self.selected.last_mut() {
+            *index = (*index).min(len.saturating_sub(1));
+        }
+    }
+}
+
+impl SharableState<AppState> {
+    /// open the explorer on the currently selected target, its tree is scanned the first time only
+    pub fn open_explorer(&self) {
+        let target = {
+            let state = self.read();
+            match state.target_directories.datas.is_empty() {
+                true => return,
+                false => state.target_directories.current().clone(),
+            }
+        };
+        if target.is_deleted {
+            return;
+        }
+
+        let mut cached = None;
+        self.mutate(|data| cached = data.explorer_cache.remove(&target.path));
+        let tree = match cached {
+            Some(tree) => tree,
+            None => {
+                self.set_message(Some(Message::new(
+                    "Computing folder sizes ⏳",
+                    MessageType::Info,
+                    None,
+                    None,
+                )));
+                let tree = DirTree::scan(Path::new(&target.path));
+                self.set_message(None);
+                tree
+            }
+        };
+
+        self.mutate(|data| data.explorer = Some(Explorer::new(target.path, tree)));
+    }
+
+    /// close the explorer, and keep its tree for the next time the target is opened
+    pub fn close_explorer(&self) {
+        self.mutate(|data| {
+            if let Some(explorer) = data.explorer.take() {
+                data.explorer_cache
+                    .insert(explorer.target_path, explorer.tree);
+            }
+        });
+    }
+
+    pub fn explorer_next(&self) {
+        self.mutate(|data| data.explorer.iter_mut().for_each(|e| e.next()))
+    }
+
+    pub fn explorer_previous(&self) {
+        self.mutate(|data| data.explorer.iter_mut().for_each(|e| e.previous()))
+    }
+
+    pub fn explorer_toggle_dependencies(&self) {
+        self.mutate(|data| {
+            data.explorer
+                .iter_mut()
+                .for_each(|e| e.toggle_dependencies())
+        })
+    }
+
+    pub fn explorer_enter(&self) {
+        self.mutate(|data| data.explorer.iter_mut().for_each(|e| e.enter()))
+    }
+
+    /// go to the parent folder, or close the explorer when already at the root of the target
+    pub fn explorer_leave(&self) {
+        let mut closed = false;
+        self.mutate(|data| {
+            if let Some(explorer) = data.explorer.as_mut() {
+                closed = !explorer.leave();
+            }
+        });
+        if closed {
+            self.close_explorer();
+        }
+    }
+
+    /// whether this file or folder of the target can be deleted, the user is told why it can't
+    fn can_delete_from(&self, target_path: &str, path: &Path) -> bool {
+        if let Some(protection) = self.target_protection(target_path) {
+            self.set_message(Some(protection));
+            return false;
+        }
+        let refusal = match self.verify_target(target_path) {
+            Ok(metadata) => metadata
+                .keep
+                .iter()
+                .find(|folder| path.starts_with(Path::new(target_path).join(folder)))
+                .map(|folder| format!("its Cargo.toml keeps {folder}")),
+            Err(e) => Some(e.to_string()),
+        };
+        let Some(reason) = refusal else {
+            return true;
+        };
+        self.set_message(Some(Message::new(
+            &format!("Can't delete it, {reason}"),
+            MessageType::Warning,
+            Some(Duration::from_secs(4)),
+            None,
+        )));
+        false
+    }
+
+    /// ask the user to confirm the deletion of the file or folder selected in the explorer
+    pub fn ask_delete_explorer_selection(&self) {
+        let (target_path, selected) = match self
+            .read()
+            .explorer
+            .as_ref()
+            .filter(|e| e.dependencies.is_none())
+            .and_then(|e| Some((e.target_path.clone(), e.selected()?.clone())))
+        {
+            Some(selection) => selection,
+            None => return,
+        };
+        if !self.can_delete_from(&target_path, &selected.path) {
+            return;
+        }
+
+        self.set_popup(Some(Popup::new(
+            "Delete",
+            vec![
+                selected.path.to_string_lossy().to_string(),
+                match self.explorer_backend() {
+                    DeleteBackend::Remove => format!(
+                        "It will permanently free {}",
+                        bytes_len_to_string_prefix(selected.size)
+                    ),
+                    backend => format!("It will be {}", backend.outcome()),
+                },
+            ],
+            PopupAction::DeleteExplorerSelection(selected.path),
+        )));
+    }
+
+    /// the backend of the app, but cargo only cleans whole targets
+    fn explorer_backend(&self) -> DeleteBackend {
+        match self.read().backend.clone() {
+            DeleteBackend::CargoClean(_) => DeleteBackend::Remove,
+            backend => backend,
+        }
+    }
+
+    /// deletes the file or folder selected in the explorer, and update the size of the explored target
+    pub fn delete_explorer_selection(&self, path: PathBuf) {
+        // nothing is attempted if the explorer was closed or moved meanwhile
+        let (target_path, size) = match self.read().explorer.as_ref().and_then(|e| {
+            let selected = e.selected().filter(|s| s.path == path)?;
+            Some((e.target_path.clone(), selected.size))
+        }) {
+            Some(selection) => selection,
+            None => return,
+        };
+        // it may have been pinned, or its Cargo.toml changed, since the deletion was confirmed
+        if !self.can_delete_from(&target_path, &path) {
+            return;
+        }
+        let backend = self.explorer_backend();
+
+        // the state isn't held while the disk is written, the ui keeps drawing
+        let mut removed = 0;
+        let result = backend.remove(&path, &[], |progress| {
+            if let RemovalProgress::Removed { bytes, .. } = progress {
+                removed = bytes
+            }
+        });
+        let freed = match backend {
+            _ if dry_run::is_enabled() => size,
+            DeleteBackend::Remove => removed,
+            _ => 0,
+        };
+        // a failure may have removed a part of it
+        let left = (!dry_run::is_enabled() && fs::symlink_metadata(&path).is_ok())
+            .then(|| DirTree::scan(&path));
+
+        let mut project = None;
+        self.mutate(|data| {
+            if let Some(explorer) = data
+                .explorer
+                .as_mut()
+                .filter(|explorer| explorer.target_path == target_path)
+            {
+                if !dry_run::is_enabled() {
+                    explorer.refresh(&path, left);
+                }
+            }
+            let new_size = data
+                .explorer
+                .as_ref()
+                .filter(|explorer| explorer.target_path == target_path)
+                .map(|explorer| explorer.tree.size);
+            if let Some(target) = data
+                .target_directories
+                .datas
+                .iter_mut()
+                .find(|t| t.path == target_path)
+            {
+                target.size_bytes = new_size.unwrap_or(target.size_bytes);
+                project = Some(target.project_name.clone());
+            }
+            data.update_total_size();
+        });
+
+        // each file left, e.g: "deps/x: Permission denied (os error 13)"
+        let failures = match &result {
+            Err(e) => e
+                .downcast_ref::<PartialRemoval>()
+                .map(|partial| partial.failures.clone())
+                .unwrap_or_default(),
+            Ok(()) => vec![],
+        };
+        if let Some(project) = project {
+            let record = AuditRecord::new(
+                AuditAction::Delete,
+                &path.to_string_lossy(),
+                &project,
+                backend.name(),
+            )
+            .freed(freed);
+            self.audit(match &result {
+                Ok(()) => record,
+                Err(e) if !failures.is_empty() => {
+                    record.failed(format!("{e}: {}", failures.join(", ")), freed > 0)
+                }
+                Err(e) => record.failed(e.to_string(), freed > 0),
+            });
+        }
+        if freed > 0 {
+            self.record_reclaimed(
+                format!("{} deleted from the explorer", path.display()),
+                freed,
+            );
+        }
+
+        let freed = bytes_len_to_string_prefix(freed);
+        match (result, &backend) {
+            (Ok(()), _) if dry_run::is_enabled() => self.set_message(Some(Message::new(
+                &format!("Dry run: {freed} would be {}", backend.outcome()),
+                MessageType::Success,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            (Ok(()), DeleteBackend::Remove) => self.set_message(Some(Message::new(
+                &format!("Deleted, {freed} freed"),
+                MessageType::Success,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            (Ok(()), _) => self.set_message(Some(Message::new(
+                &format!("{} {}", bytes_len_to_string_prefix(size), backend.outcome()),
+                MessageType::Success,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+            (Err(e), _) => self.set_message(Some(Message::new(
+                &match failures.first() {
+                    Some(first) => format!(
+                        "Deleted {freed}, {} files couldn't be removed, e.g: {first}",
+                        failures.len()
+                    ),
+                    None => format!("Failed to delete: {e}"),
+                },
+                MessageType::Error,
+                None,
+                None,
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod explorer_tests {
+    use std::path::{Path, PathBuf};
+
+    use super::DirTree;
+
+    fn leaf(path: &str, size: u64) -> DirTree {
+        DirTree {
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            path: PathBuf::from(path),
+            size,
+            is_dir: false,
+            children: vec![],
+        }
+    }
+
+    #[test]
+    fn test_replace_node() {
+        let mut tree = DirTree {
+            name: "target".to_string(),
+            path: PathBuf::from("target"),
+            size: 60,
+            is_dir: true,
+            children: vec![
+                DirTree {
+                    name: "debug".to_string(),
+                    path: PathBuf::from("target/debug"),
+                    size: 50,
+                    is_dir: true,
+                    children: vec![
+                        leaf("target/debug/deps", 40),
+                        leaf("target/debug/build", 10),
+                    ],
+                },
+                leaf("target/release", 10),
+            ],
+        };
+
+        assert_eq!(tree.node(&[0, 1]).map(|n| n.name.as_str()), Some("build"));
+        assert_eq!(
+            tree.indexes_of(Path::new("target/debug/build")),
+            Some(vec![0, 1])
+        );
+        assert_eq!(tree.indexes_of(Path::new("target/doc")), None);
+        assert_eq!(tree.replace(&[0, 0], None).map(|n| n.size), Some(40));
+        assert_eq!(tree.size, 20);
+        assert_eq!(tree.children[0].size, 10);
+        assert_eq!(tree.children[0].children.len(), 1);
+        assert!(tree.replace(&[5], None).is_none());
+        assert_eq!(
+            tree.replace(&[0, 0], Some(leaf("target/debug/build", 4)))
+                .map(|n| n.size),
+            Some(10)
+        );
+        assert_eq!((tree.size, tree.children[0].size), (14, 4));
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use crate::{
+    audit::{self, AuditRecord, HistoryQuery},
+    ui::components::{
+        list_with_state::ListWithState,
+        message::{Message, MessageType},
+    },
+    utils::sharable_state::SharableState,
+};
+
+use super::AppState;
+
+/// Lists the audit log, last record first, it replaces the list while opened
+pub struct HistoryView {
+    pub records: ListWithState<AuditRecord>,
+}
+
+impl SharableState<AppState> {
+    pub fn open_history(&self) {
+        let mut records = match audit::history(&HistoryQuery::default()) {
+            Ok(records) => records,
+            Err(e) => {
+                return self.set_message(Some(Message::new(
+                    &format!("Couldn't read the audit log: {e}"),
+                    MessageType::Error,
+                    Some(Duration::from_secs(3)),
+                    None,
+                )))
+            }
+        };
+        records.reverse();
+        self.mutate(|data| {
+            data.history = Some(HistoryView {
+                records: ListWithState {
+                    index: 0,
+                    datas: records,
+                },
+            })
+        });
+    }
+
+    pub fn close_history(&self) {
+        self.mutate(|data| data.history = None)
+    }
+
+    pub fn history_next(&self) {
+        self.mutate(|data| data.history.iter_mut().for_each(|h| h.records.next()))
+    }
+
+    pub fn history_previous(&self) {
+        self.mutate(|data| data.history.iter_mut().for_each(|h| h.records.previous()))
+    }
+
+    /// append the record to the audit log, a failure to write it doesn't undo anything so it's only shown
+    pub fn audit(&self, record: AuditRecord) {
+        if let Err(e) = audit::log(&record) {
+            self.set_message(Some(Message::new(
+                &format!("Couldn't write the audit log: {e}"),
+                MessageType::Warning,
+                Some(Duration::from_secs(5)),
+                None,
+            )))
+        }
+    }
+}
@@ -1,22 +1,51 @@
+pub mod analysis;
+pub mod budget;
 mod core;
+pub mod deletion;
+pub mod explorer;
+pub mod history;
 mod parse;
+pub mod pins;
+pub mod predicate;
+pub mod restore;
+mod selection;
+pub mod session;
+
+pub use self::core::{collect_target_dirs, scan_target_dirs};
 
 use std::{
-    fs,
-    sync::Arc,
+    collections::HashMap,
+    sync::{mpsc::Sender, Arc},
     thread,
     time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use chrono::{DateTime, Local};
 use crossterm::event::{self, Event, KeyCode};
 use tui::{backend::Backend, Terminal};
 
 use crate::{
+    app::{
+        analysis::Analysis,
+        budget::BudgetProgress,
+        deletion::{
+            spawn_deletion_worker, spawn_lock_watcher, DeletionJob, DeletionProgress,
+            FailedDeletion,
+        },
+        explorer::{DirTree, Explorer},
+        history::HistoryView,
+        predicate::{Predicate, SelectionPrompt},
+        restore::RestoreView,
+        session::SessionStats,
+    },
+    backends::{cargo_clean::CleanOptions, DeleteBackend},
+    config::{self, keymap::Action, project::Protection, ConfirmPolicy},
     ui::{
         components::{
             list_with_state::ListWithState,
             message::{Message, MessageAction, MessageType},
+            popup::Popup,
         },
         ui,
     },
@@ -30,34 +59,135 @@ pub struct TargetDir {
     pub path: String,
     /// rust associated project name in the Cargo.toml
     pub project_name: String,
-    pub last_modified: String,
+    /// the Cargo.toml next to the target
+    pub manifest_path: String,
+    pub kind: TargetKind,
+    /// when the folder was last written, i.e: last built
+    pub last_modified: DateTime<Local>,
     /// Is user deleted the target file
     pub is_deleted: bool,
-    /// formatted size of the folder, e.g: "5 GiB", "28 KiB"...
-    pub size: String,
+    /// Is user marked the target for deletion, nothing is deleted until the user confirms the marked targets
+    pub is_marked: bool,
+    /// size of the folder in bytes
+    pub size_bytes: u64,
+    /// bytes of artifacts built by rustc versions that aren't installed anymore, measured after the search
+    pub outdated_toolchains_size: u64,
+    /// set while the folder is in the deletion queue
+    pub deletion: Option<DeletionProgress>,
+    /// a cargo process holds the lock of the folder (a build, or rust-analyzer), probed every few seconds
+    pub is_busy: bool,
+    /// set when its last deletion failed, what's left of the folder stays on the disk
+    pub failed_deletion: Option<FailedDeletion>,
+    /// whether its project has uncommitted changes, `None` until it's checked after the search or if it isn't in a git repository
+    pub has_git_changes: Option<bool>,
+    /// why it can't be deleted, set when its Cargo.toml or the config protects it
+    pub protection: Option<Protection>,
+    /// the user pinned its project, it's never marked nor deleted until it's unpinned
+    pub is_pinned: bool,
 }
 
-impl TargetDir {
-    /// deletes permanently the folder from the user disk
-    pub fn delete(&mut self) -> Result<()> {
-        if self.is_deleted {
-            return Err(anyhow!("folder already deleted"));
-        }
+/// What the Cargo.toml next to a target describes
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    /// a single crate, its `[package]`
+    #[default]
+    Package,
+    /// the shared target of a `[workspace]`
+    Workspace,
+}
 
-        fs::remove_dir_all(self.path.clone())?;
-        self.is_deleted = true;
-        Ok(())
+impl TargetKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetKind::Package => "package",
+            TargetKind::Workspace => "workspace",
+        }
     }
 }
 
 /// Application public variables, persist after frame rebuild
 #[derive(Default)]
 pub struct AppState {
-    pub root_dir: Option<String>,
+    /// folders searched for the targets, the current directory by default
+    pub root_dirs: Vec<String>,
+    /// criteria every listed target must match, given on the command line
+    pub filters: Vec<Predicate>,
     pub target_directories: ListWithState<TargetDir>,
     pub searching: bool,
     pub message: Option<Message>,
-    pub total_size: String,
+    /// confirmation popup waiting for the user answer, it captures all the keys while it's displayed
+    pub popup: Option<Popup>,
+    /// criteria typed to mark the matching targets, it captures all the keys while it's displayed
+    pub selection_prompt: Option<SelectionPrompt>,
+    /// bytes of the targets still on the disk
+    pub total_size: u64,
+    /// target dir currently explored, it replaces the list while opened
+    pub explorer: Option<Explorer>,
+    /// trees of the target dirs already explored, so that they're only scanned once per session
+    pub explorer_cache: HashMap<String, DirTree>,
+    /// crates compiled redundantly across all the targets, it replaces the list while opened
+    pub analysis: Option<Analysis>,
+    /// the analysis reads every target in the background, pressing its key again meanwhile doesn't start another one
+    pub analysing: bool,
+    /// Space deletes the selected target right away instead of marking it, enabled with `--instant-delete`
+    pub instant_delete: bool,
+    /// whether deleting the marked targets asks first, `confirm` in the config or `--confirm`
+    pub confirm: ConfirmPolicy,
+    /// how the targets are deleted, chosen with `--backend`
+    pub backend: DeleteBackend,
+    /// the targets protected by their Cargo.toml can be deleted, enabled with `--ignore-protection`
+    pub ignore_protection: bool,
+    /// how long quarantined targets are kept before their purge
+    pub grace_period: Duration,
+    /// what the cargo clean backend removes
+    pub clean_options: CleanOptions,
+    /// targets moved to the trash that can be restored, it replaces the list while opened
+    pub restore: Option<RestoreView>,
+    /// what rtkill deleted in this session and the previous ones, it replaces the list while opened
+    pub history: Option<HistoryView>,
+    /// target dirs are deleted one by one by a worker, so that the ui never waits for them
+    pub deletion_queue: Option<Sender<DeletionJob>>,
+    /// what was removed since rtkill started, and the space it freed
+    pub session: SessionStats,
+    /// space the user asked to free with `f`, until the deletions it planned are done
+    pub budget: Option<BudgetProgress>,
+}
+
+/// what the keys act on, read from the state once a frame
+enum Mode {
+    SelectionPrompt,
+    Popup,
+    Explorer,
+    Analysis,
+    Restore,
+    History,
+    List {
+        instant_delete: bool,
+        searching: bool,
+    },
+}
+
+impl Mode {
+    fn of(state: &AppState) -> Self {
+        if state.selection_prompt.is_some() {
+            Mode::SelectionPrompt
+        } else if state.popup.is_some() {
+            Mode::Popup
+        } else if state.explorer.is_some() {
+            Mode::Explorer
+        } else if state.analysis.is_some() {
+            Mode::Analysis
+        } else if state.restore.is_some() {
+            Mode::Restore
+        } else if state.history.is_some() {
+            Mode::History
+        } else {
+            Mode::List {
+                instant_delete: state.instant_delete,
+                searching: state.searching,
+            }
+        }
+    }
 }
 
 /// launch app, and begin frame
@@ -65,6 +195,18 @@ pub fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     state: Arc<SharableState<AppState>>,
 ) -> Result<()> {
+    {
+        let queue = spawn_deletion_worker(Arc::clone(&state));
+        state.mutate(|data| data.deletion_queue = Some(queue));
+        spawn_lock_watcher(Arc::clone(&state));
+    }
+
+    // the quarantined targets whose grace period ended while rtkill wasn't running
+    {
+        let state_purge = Arc::clone(&state);
+        thread::spawn(move || state_purge.purge_expired_quarantine());
+    }
+
     // search in parallel for target folders, and stream the data as it comes
     {
         let state_search = Arc::clone(&state);
@@ -73,58 +215,161 @@ pub fn run_app<B: Backend>(
 
     let mut last_time_user_active = Instant::now();
     loop {
-        let current_appstate = Arc::new(state.read()); // I wrap it in Arc to prevent calling the read unsafe function several times within a frame (loop iteration), like this data is updated once every frame
+        // the state is only read while drawing, the key handlers write to it
+        let (mode, should_quit) = {
+            let current_appstate = state.read();
 
-        // build and show ui
-        let ui_state = Arc::clone(&current_appstate);
-        terminal.draw(|f| ui(f, &ui_state))?;
+            // build and show ui
+            terminal.draw(|f| ui(f, &current_appstate))?;
 
-        // check message deletion
-        if let Some(msg) = &current_appstate.message {
-            if msg.should_be_deleted() {
-                if let Some(MessageAction::Quit) = &msg.action_when_deleted {
-                    return Ok(());
-                }
+            (
+                Mode::of(&current_appstate),
+                current_appstate
+                    .message
+                    .as_ref()
+                    .filter(|msg| msg.should_be_deleted())
+                    .map(|msg| matches!(msg.action_when_deleted, Some(MessageAction::Quit))),
+            )
+        };
 
-                state.set_message(None);
-            }
+        // check message deletion
+        match should_quit {
+            Some(true) => return Ok(()),
+            Some(false) => state.set_message(None),
+            None => (),
         }
 
-        // check events
-        let refresh_rate = match last_time_user_active.elapsed().as_secs() >= 10 {
-            true => 1000,
-            false => 100,
-        }; // if user active, refresh view every 1 second, otherwise 10 time per second
-        if event::poll(Duration::from_millis(refresh_rate))? {
+        // check events, the screen is refreshed less often once the user is idle for 10 seconds
+        let idle = last_time_user_active.elapsed().as_secs() >= 10;
+        if event::poll(config::get().ui.refresh_rate(idle))? {
             if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Up => state.prev_item(),
-                    KeyCode::Down => state.next_item(),
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Char('o') => {
-                        let path_to_open = &current_appstate.target_directories.current().path;
-                        if open::that(path_to_open).is_err() {
-                            state.set_message(Some(Message::new(
-                                "Couldn't open path in your file explorer",
-                                MessageType::Warning,
-                                Some(Duration::from_secs(2)),
-                                None,
-                            )))
+                if let Mode::SelectionPrompt = mode {
+                    match key.code {
+                        KeyCode::Char(c) => state.selection_prompt_push(c),
+                        KeyCode::Backspace => state.selection_prompt_pop(),
+                        KeyCode::Tab => state.selection_prompt_next_policy(),
+                        KeyCode::Enter => state.apply_selection_prompt(),
+                        KeyCode::Esc => state.close_selection_prompt(),
+                        _ => (),
+                    };
+                } else if let Mode::Popup = mode {
+                    match key.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            let state_popup = Arc::clone(&state);
+                            thread::spawn(move || state_popup.confirm_popup());
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => state.set_popup(None),
+                        KeyCode::Char('h') => state.toggle_dedupe_link(),
+                        _ => (),
+                    };
+                } else if let Mode::Explorer = mode {
+                    match key.code {
+                        KeyCode::Up => state.explorer_previous(),
+                        KeyCode::Down => state.explorer_next(),
+                        KeyCode::Enter => state.explorer_enter(),
+                        KeyCode::Backspace => state.explorer_leave(),
+                        KeyCode::Char('c') => state.explorer_toggle_dependencies(),
+                        KeyCode::Esc => state.close_explorer(),
+                        KeyCode::Char(' ') => state.ask_delete_explorer_selection(),
+                        KeyCode::Char('q') => return Ok(()),
+                        _ => (),
+                    };
+                } else if let Mode::Analysis = mode {
+                    match key.code {
+                        KeyCode::Up => state.analysis_previous(),
+                        KeyCode::Down => state.analysis_next(),
+                        KeyCode::Esc | KeyCode::Char('a') => state.close_analysis(),
+                        KeyCode::Char('q') => return Ok(()),
+                        _ => (),
+                    };
+                } else if let Mode::Restore = mode {
+                    match key.code {
+                        KeyCode::Up => state.restore_previous(),
+                        KeyCode::Down => state.restore_next(),
+                        KeyCode::Enter => state.restore_selected(),
+                        KeyCode::Char('p') => {
+                            let state_purge = Arc::clone(&state);
+                            thread::spawn(move || state_purge.purge_selected());
+                        }
+                        KeyCode::Esc | KeyCode::Char('u') => state.close_restore_view(),
+                        KeyCode::Char('q') => return Ok(()),
+                        _ => (),
+                    };
+                } else if let Mode::History = mode {
+                    match key.code {
+                        KeyCode::Up => state.history_previous(),
+                        KeyCode::Down => state.history_next(),
+                        KeyCode::Esc | KeyCode::Char('h') => state.close_history(),
+                        KeyCode::Char('q') => return Ok(()),
+                        _ => (),
+                    };
+                } else if let Mode::List {
+                    instant_delete,
+                    searching,
+                } = mode
+                {
+                    match config::get().keymap.action(key.code) {
+                        Some(Action::Up) => state.prev_item(),
+                        Some(Action::Down) => state.next_item(),
+                        Some(Action::Quit) => return Ok(()),
+                        Some(Action::Open) => {
+                            let path_to_open =
+                                state.read().target_directories.current().path.clone();
+                            if open::that(path_to_open).is_err() {
+                                state.set_message(Some(Message::new(
+                                    "Couldn't open path in your file explorer",
+                                    MessageType::Warning,
+                                    Some(Duration::from_secs(2)),
+                                    None,
+                                )))
+                            }
+                        }
+                        Some(Action::Mark) if instant_delete => state.delete_current_item(),
+                        Some(Action::Mark) => state.toggle_mark_current_item(),
+                        Some(Action::MarkAll) => state.mark_all(),
+                        Some(Action::Pin) => state.toggle_pin_current_item(),
+                        Some(Action::InvertMarks) => state.invert_marks(),
+                        Some(Action::MarkByCriteria) => state.open_selection_prompt(),
+                        Some(Action::FreeSpace) => state.open_budget_prompt(),
+                        Some(Action::DeleteMarked) => state.ask_delete_marked(),
+                        Some(Action::DeletionErrors) => state.ask_retry_deletion(),
+                        Some(Action::Backend) => state.next_backend(),
+                        Some(Action::Restore) => state.open_restore_view(),
+                        Some(Action::History) => state.open_history(),
+                        Some(Action::Undo) => state.undo_last_quarantine(),
+                        Some(Action::Explore) => {
+                            let state_explorer = Arc::clone(&state);
+                            thread::spawn(move || state_explorer.open_explorer());
+                        }
+                        Some(Action::Prune) => {
+                            let state_prune = Arc::clone(&state);
+                            thread::spawn(move || state_prune.prune_current_item());
+                        }
+                        Some(Action::Analysis) if !searching => {
+                            let state_analysis = Arc::clone(&state);
+                            thread::spawn(move || state_analysis.open_analysis());
+                        }
+                        Some(Action::Dedupe) if !searching => {
+                            let state_dedupe = Arc::clone(&state);
+                            thread::spawn(move || state_dedupe.dedupe_targets());
+                        }
+                        Some(Action::PruneToolchains) => {
+                            let state_prune = Arc::clone(&state);
+                            thread::spawn(move || {
+                                state_prune.prune_outdated_toolchains_current_item()
+                            });
                         }
-                    }
-                    KeyCode::Char(' ') => state.delete_current_item(),
-                    KeyCode::Char('r') => {
                         // to avoid user to spam refresh, which could cause memory issue
-                        if !current_appstate.searching {
+                        Some(Action::Refresh) if !searching => {
                             state.clear_list();
                             {
                                 let state_search = Arc::clone(&state);
                                 thread::spawn(move || state_search.search());
                             }
                         }
-                    }
-                    _ => (),
-                };
+                        _ => (),
+                    };
+                }
             }
             last_time_user_active = Instant::now();
         }
@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::{
+    config::{self, keymap::Action},
+    ui::components::{
+        list_with_state::ListWithState,
+        message::{Message, MessageType},
+        popup::{Popup, PopupAction},
+    },
+    utils::{sharable_state::SharableState, state_dir},
+};
+
+use super::{AppState, TargetDir};
+
+/// file of the state dir listing the pinned projects, one canonical manifest path per line
+const PINS_FILE: &str = "pins";
+
+/// Projects pinned from the app, their targets are never marked nor deleted, in this session and the next ones
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Pins(BTreeSet<PathBuf>);
+
+impl Pins {
+    /// the pins saved by the previous sessions, none if nothing was ever pinned
+    pub fn load() -> Self {
+        state_dir()
+            .and_then(|dir| Ok(fs::read_to_string(dir.join(PINS_FILE))?))
+            .map(|pins| Self::parse(&pins))
+            .unwrap_or_default()
+    }
+
+    fn parse(pins: &str) -> Self {
+        Self(
+            pins.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PathBuf::from)
+                .collect(),
+        )
+    }
+
+    pub fn contains(&self, manifest_path: &Path) -> bool {
+        self.0.contains(&canonical(manifest_path))
+    }
+
+    /// pin or unpin the project, the pins are read again first so that another session's aren't lost
+    pub fn update(manifest_path: &Path, pinned: bool) -> Result<()> {
+        let mut pins = Self::load();
+        match pinned {
+            true => pins.0.insert(canonical(manifest_path)),
+            false => pins.0.remove(&canonical(manifest_path)),
+        };
+        pins.save()
+    }
+
+    /// written aside then renamed, so that a crash can't leave half of the pins
+    fn save(&self) -> Result<()> {
+        let dir = state_dir()?;
+        fs::create_dir_all(&dir)?;
+        let pins = self
+            .0
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect::<String>();
+        let temporary = dir.join(format!("{PINS_FILE}.tmp"));
+        fs::write(&temporary, pins)?;
+        fs::rename(temporary, dir.join(PINS_FILE))?;
+        Ok(())
+    }
+}
+
+/// the same project may be reached through a symlink
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or(path.to_path_buf())
+}
+
+impl ListWithState<TargetDir> {
+    /// the pinned targets go first, in their own section, the selected target stays selected
+    pub fn sort_pinned(&mut self) {
+        let selected = self.datas.get(self.index).map(|t| t.path.clone());
+        self.datas.sort_by_key(|t| !t.is_pinned);
+        if let Some(selected) = selected {
+            self.index = self
+                .datas
+                .iter()
+                .position(|t| t.path == selected)
+                .unwrap_or(0);
+        }
+    }
+
+    /// number of targets in the pinned section, at the top of the list
+    pub fn pinned_count(&self) -> usize {
+        self.datas.iter().take_while(|t| t.is_pinned).count()
+    }
+}
+
+impl SharableState<AppState> {
+    /// pin the selected target so that it's never marked nor deleted, unpinning it asks for a confirmation first
+    pub fn toggle_pin_current_item(&self) {
+        let target = {
+            let state = self.read();
+            match state
+                .target_directories
+                .datas
+                .get(state.target_directories.index)
+            {
+                Some(target) if !target.is_deleted => target.clone(),
+                _ => return,
+            }
+        };
+
+        if target.is_pinned {
+            return self.set_popup(Some(Popup::new(
+                "Unpin",
+                vec![
+                    format!("{}: {}", target.project_name, target.manifest_path),
+                    "Its target could be marked and deleted again".to_string(),
+                ],
+                PopupAction::Unpin(target.manifest_path),
+            )));
+        }
+        self.set_pinned(&target.manifest_path, true);
+    }
+
+    /// save the pin, and move the targets of the project to their section
+    pub fn set_pinned(&self, manifest_path: &str, pinned: bool) {
+        if let Err(e) = Pins::update(Path::new(manifest_path), pinned) {
+            return self.set_message(Some(Message::new(
+                &format!("Couldn't save the pins: {e}"),
+                MessageType::Error,
+                Some(Duration::from_secs(4)),
+                None,
+            )));
+        }
+
+        let mut project_name = String::new();
+        self.mutate(|data| {
+            for target in data
+                .target_directories
+                .datas
+                .iter_mut()
+                .filter(|t| t.manifest_path == manifest_path)
+            {
+                target.is_pinned = pinned;
+                target.is_marked &= !pinned;
+                project_name = target.project_name.clone();
+            }
+            data.target_directories.sort_pinned();
+        });
+        self.set_message(Some(Message::new(
+            &match pinned {
+                true => format!(
+                    "{project_name} is pinned, it won't be marked nor deleted, {} to unpin it",
+                    config::get().keymap.key_name(Action::Pin)
+                ),
+                false => format!("{project_name} isn't pinned anymore"),
+            },
+            MessageType::Info,
+            Some(Duration::from_secs(3)),
+            None,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod pins_tests {
+    use std::path::Path;
+
+    use crate::{app::TargetDir, ui::components::list_with_state::ListWithState};
+
+    use super::Pins;
+
+    #[test]
+    fn test_parse_pins() {
+        let pins = Pins::parse("/a/Cargo.toml\n\n/b/Cargo.toml\n");
+        assert!(pins.contains(Path::new("/a/Cargo.toml")));
+        assert!(pins.contains(Path::new("/b/Cargo.toml")));
+        assert!(!pins.contains(Path::new("/c/Cargo.toml")));
+    }
+
+    #[test]
+    fn test_pinned_section() {
+        let mut list = ListWithState {
+            index: 1,
+            datas: ["a", "b", "c"]
+                .iter()
+                .map(|name| TargetDir {
+                    path: name.to_string(),
+                    is_pinned: *name == "c",
+                    ..Default::default()
+                })
+                .collect(),
+        };
+        list.sort_pinned();
+        let paths = list
+            .datas
+            .iter()
+            .map(|t| t.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["c", "a", "b"]);
+        assert_eq!(list.current().path, "b");
+        assert_eq!(list.pinned_count(), 1);
+    }
+}
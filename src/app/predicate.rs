@@ -0,0 +1,420 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use glob::Pattern;
+
+use crate::{
+    ui::components::message::{Message, MessageType},
+    utils::{bytes_len_to_string_prefix, parse_bytes, sharable_state::SharableState},
+};
+
+use super::{budget::BudgetPolicy, AppState, TargetDir};
+
+/// shown under the prompt input
+pub const PREDICATES_SYNTAX: &str =
+    "older:60d (or 8w, 12h)  larger:2G  under:~/archive  name:foo  match:GLOB  exclude:GLOB  clean (no git changes)";
+
+/// A criteria a target must match to be marked by the selection prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// last built more than this long ago
+    OlderThan(chrono::Duration),
+    /// bigger than this many bytes
+    LargerThan(u64),
+    /// somewhere in this folder
+    Under(PathBuf),
+    /// part of the project name
+    NameContains(String),
+    /// the project name or the path matches the glob, e.g: "*/work/**"
+    Matches(Pattern),
+    /// neither the project name nor the path matches the glob
+    Excludes(Pattern),
+    /// its project has no uncommitted change, nothing is lost if it has to be rebuilt from git
+    NoGitChanges,
+}
+
+impl Predicate {
+    pub fn matches(&self, target: &TargetDir, now: DateTime<Local>) -> bool {
+        match self {
+            Predicate::OlderThan(age) => now - target.last_modified > *age,
+            Predicate::LargerThan(size) => target.size_bytes > *size,
+            Predicate::Under(dir) => Path::new(&target.path).starts_with(dir),
+            Predicate::NameContains(name) => target.project_name.contains(name.as_str()),
+            Predicate::Matches(glob) => glob_matches(glob, target),
+            Predicate::Excludes(glob) => !glob_matches(glob, target),
+            Predicate::NoGitChanges => target.has_git_changes == Some(false),
+        }
+    }
+}
+
+/// whether the target matches every predicate, its git status is checked first if one of them needs it
+pub fn matches_all(predicates: &[Predicate], target: &mut TargetDir) -> bool {
+    if predicates.contains(&Predicate::NoGitChanges) && target.has_git_changes.is_none() {
+        target.has_git_changes = has_git_changes(Path::new(&target.path));
+    }
+    let now = Local::now();
+    predicates.iter().all(|p| p.matches(target, now))
+}
+
+/// parse the prompt input, criteria are separated by spaces and a target must match all of them
+///
+/// relative paths are relative to `root`, the first scanned directory
+pub fn parse_predicates(input: &str, root: &Path) -> Result<Vec<Predicate>> {
+    let predicates = input
+        .split_whitespace()
+        .map(|criteria| {
+            let (key, value) = criteria.split_once(':').unwrap_or((criteria, ""));
+            match (key, value) {
+                ("older", age) => Ok(Predicate::OlderThan(parse_age(age)?)),
+                ("larger", size) => Ok(Predicate::LargerThan(parse_bytes(size)?)),
+                ("under", dir) if !dir.is_empty() => Ok(Predicate::Under(expand_path(dir, root))),
+                ("name", name) if !name.is_empty() => Ok(Predicate::NameContains(name.to_string())),
+                ("match", glob) if !glob.is_empty() => Ok(Predicate::Matches(parse_glob(glob)?)),
+                ("exclude", glob) if !glob.is_empty() => Ok(Predicate::Excludes(parse_glob(glob)?)),
+                ("clean", "") => Ok(Predicate::NoGitChanges),
+                _ => Err(anyhow!("'{criteria}' isn't a criteria")),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match predicates.is_empty() {
+        true => Err(anyhow!("Type some criteria, e.g: older:60d larger:2G")),
+        false => Ok(predicates),
+    }
+}
+
+/// e.g: "60d", "8w", "12h", or "60" for days
+pub fn parse_age(age: &str) -> Result<chrono::Duration> {
+    let (number, unit) = age.split_at(age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len()));
+    let number = number
+        .parse::<i64>()
+        .map_err(|_| anyhow!("'{age}' isn't an age, e.g: 60d, 8w or 12h"))?;
+    let hours = match unit {
+        "" | "d" => 24,
+        "w" => 7 * 24,
+        "h" => 1,
+        _ => return Err(anyhow!("unknown unit in '{age}', expected d, w or h")),
+    };
+    // chrono panics past i64::MAX milliseconds
+    number
+        .checked_mul(hours * 3600 * 1000)
+        .map(chrono::Duration::milliseconds)
+        .ok_or(anyhow!("'{age}' is too long"))
+}
+
+pub fn parse_glob(glob: &str) -> Result<Pattern> {
+    Pattern::new(glob).map_err(|e| anyhow!("'{glob}' isn't a valid glob: {e}"))
+}
+
+fn glob_matches(glob: &Pattern, target: &TargetDir) -> bool {
+    glob.matches(&target.project_name) || glob.matches_path(Path::new(&target.path))
+}
+
+/// expand `~` to the home folder, and make relative paths relative to `root`
+pub fn expand_path(path: &str, root: &Path) -> PathBuf {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => root.join(path),
+    }
+}
+
+/// `Some(true)` if the project owning the target has uncommitted changes, `None` if it isn't in a git repository
+///
+/// the target itself isn't counted, some projects don't ignore it
+pub fn has_git_changes(target: &Path) -> Option<bool> {
+    let project = target.parent()?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(project)
+        .args(["status", "--porcelain", "--", ".", ":(exclude)target"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => Some(!output.stdout.is_empty()),
+        false => None,
+    }
+}
+
+/// What the selection prompt does with its input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PromptMode {
+    /// the input is criteria, every matching target is marked
+    #[default]
+    Criteria,
+    /// the input is a budget, the targets to delete to meet it are chosen by the policy
+    Budget(BudgetPolicy),
+}
+
+/// Text input marking every target that matches its criteria, the matches are previewed as it's typed
+#[derive(Debug)]
+pub struct SelectionPrompt {
+    pub mode: PromptMode,
+    pub input: String,
+    /// how many targets match the input and their total size, or why it can't be parsed
+    pub preview: Result<(usize, u64), String>,
+    /// shown under the preview, e.g: when deleting every target isn't enough to meet the budget
+    pub warning: Option<String>,
+}
+
+impl Default for SelectionPrompt {
+    fn default() -> Self {
+        Self {
+            mode: PromptMode::default(),
+            input: String::new(),
+            preview: Ok((0, 0)),
+            warning: None,
+        }
+    }
+}
+
+impl AppState {
+    /// indexes of the targets still on the disk matching every criteria of the input, the protected ones left out
+    fn matching_targets(&self, input: &str) -> Result<Vec<usize>> {
+        let root = PathBuf::from(self.root_dirs.first().cloned().unwrap_or_default());
+        let predicates = parse_predicates(input, &root)?;
+        let now = Local::now();
+        Ok(self
+            .target_directories
+            .datas
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| {
+                !t.is_deleted
+                    && !t.is_protected(self.ignore_protection)
+                    && predicates.iter().all(|p| p.matches(t, now))
+            })
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    pub(super) fn update_prompt_preview(&mut self) {
+        let Some((mode, input)) = self
+            .selection_prompt
+            .as_ref()
+            .map(|p| (p.mode, p.input.clone()))
+        else {
+            return;
+        };
+        let (preview, warning) = match mode {
+            PromptMode::Criteria => {
+                let preview = self.matching_targets(&input).map(|matches| {
+                    let size = matches
+                        .iter()
+                        .map(|&i| self.target_directories.datas[i].size_bytes)
+                        .sum();
+                    (matches.len(), size)
+                });
+                (preview, None)
+            }
+            PromptMode::Budget(policy) => match self.budget_plan(&input, policy) {
+                Ok((_, plan)) => (
+                    Ok((plan.paths.len(), plan.size)),
+                    (!plan.reachable)
+                        .then(|| "Deleting all of them isn't enough to meet it".to_string()),
+                ),
+                Err(e) => (Err(e), None),
+            },
+        };
+        if let Some(prompt) = &mut self.selection_prompt {
+            prompt.preview = preview.map_err(|e| e.to_string());
+            prompt.warning = warning;
+        }
+    }
+}
+
+impl SharableState<AppState> {
+    pub fn open_selection_prompt(&self) {
+        self.mutate(|data| {
+            data.selection_prompt = Some(SelectionPrompt::default());
+            data.update_prompt_preview();
+        })
+    }
+
+    pub fn close_selection_prompt(&self) {
+        self.mutate(|data| data.selection_prompt = None)
+    }
+
+    pub fn selection_prompt_push(&self, c: char) {
+        self.mutate(|data| {
+            data.selection_prompt
+                .iter_mut()
+                .for_each(|p| p.input.push(c));
+            data.update_prompt_preview();
+        })
+    }
+
+    pub fn selection_prompt_pop(&self) {
+        self.mutate(|data| {
+            data.selection_prompt.iter_mut().for_each(|p| {
+                p.input.pop();
+            });
+            data.update_prompt_preview();
+        })
+    }
+
+    /// mark the targets matching the prompt, on top of the ones already marked, the prompt stays opened if the input is wrong
+    pub fn apply_selection_prompt(&self) {
+        let input = {
+            let state = self.read();
+            let Some(prompt) = &state.selection_prompt else {
+                return;
+            };
+            if let PromptMode::Budget(policy) = prompt.mode {
+                drop(state);
+                return self.apply_budget_prompt(policy);
+            }
+            prompt.input.clone()
+        };
+
+        // matched and marked under the same lock, a search still running inserts targets meanwhile
+        let mut marked = None;
+        self.mutate(|data| {
+            let paths = match data.matching_targets(&input) {
+                Ok(matches) => matches
+                    .into_iter()
+                    .map(|i| data.target_directories.datas[i].path.clone())
+                    .collect::<HashSet<_>>(),
+                Err(_) => return,
+            };
+            let (mut count, mut size) = (0, 0);
+            for target in data
+                .target_directories
+                .datas
+                .iter_mut()
+                .filter(|t| paths.contains(&t.path))
+            {
+                target.is_marked = true;
+                count += 1;
+                size += target.size_bytes;
+            }
+            data.selection_prompt = None;
+            marked = Some((count, size));
+        });
+        // the input isn't valid criteria, the prompt stays open
+        let Some((count, size)) = marked else {
+            return;
+        };
+        self.set_message(Some(Message::new(
+            &format!(
+                "Marked {count} targets ({}), x to delete them",
+                bytes_len_to_string_prefix(size)
+            ),
+            MessageType::Info,
+            Some(Duration::from_secs(3)),
+            None,
+        )));
+    }
+
+    /// run `git status` in the project of every found target, so that the `clean` criteria can be used, it's done after the search since it spawns a process per project
+    pub fn measure_git_changes(&self) {
+        let paths = self
+            .read()
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| !t.is_deleted && t.has_git_changes.is_none())
+            .map(|t| t.path.clone())
+            .collect::<Vec<_>>();
+
+        // every project is checked before the state is written, once
+        let changes = paths
+            .into_iter()
+            .map(|path| {
+                let changes = has_git_changes(Path::new(&path));
+                (path, changes)
+            })
+            .collect::<HashMap<_, _>>();
+        if changes.is_empty() {
+            return;
+        }
+        self.mutate(|data| {
+            for target in data.target_directories.datas.iter_mut() {
+                if let Some(changes) = changes.get(&target.path) {
+                    target.has_git_changes = *changes;
+                }
+            }
+            data.update_prompt_preview();
+        });
+    }
+}
+
+#[cfg(test)]
+mod predicate_tests {
+    use std::path::{Path, PathBuf};
+
+    use chrono::{Duration, Local};
+    use glob::Pattern;
+
+    use crate::app::TargetDir;
+
+    use super::{parse_predicates, Predicate};
+
+    #[test]
+    fn test_parse_predicates() {
+        let root = Path::new("/dev");
+        assert_eq!(
+            parse_predicates(
+                "older:60d  larger:2G under:archive name:foo match:foo-* exclude:*/tmp/* clean",
+                root
+            )
+            .unwrap(),
+            vec![
+                Predicate::OlderThan(Duration::days(60)),
+                Predicate::LargerThan(2 * 2_u64.pow(30)),
+                Predicate::Under(PathBuf::from("/dev/archive")),
+                Predicate::NameContains("foo".to_string()),
+                Predicate::Matches(Pattern::new("foo-*").unwrap()),
+                Predicate::Excludes(Pattern::new("*/tmp/*").unwrap()),
+                Predicate::NoGitChanges,
+            ]
+        );
+        assert_eq!(
+            parse_predicates("older:8w", root).unwrap(),
+            vec![Predicate::OlderThan(Duration::weeks(8))]
+        );
+        assert!(parse_predicates("", root).is_err());
+        assert!(parse_predicates("older:soon", root).is_err());
+        assert!(parse_predicates("older:999999999999999", root).is_err());
+        assert!(parse_predicates("bigger:2G", root).is_err());
+        assert!(parse_predicates("clean:yes", root).is_err());
+        assert!(parse_predicates("match:[", root).is_err());
+    }
+
+    #[test]
+    fn test_predicate_matches() {
+        let now = Local::now();
+        let target = TargetDir {
+            path: "/dev/archive/a/target".to_string(),
+            project_name: "foo-bar".to_string(),
+            last_modified: now - Duration::days(90),
+            size_bytes: 3 * 2_u64.pow(30),
+            has_git_changes: Some(false),
+            ..Default::default()
+        };
+        let root = Path::new("/dev");
+        let matches = |input| {
+            parse_predicates(input, root)
+                .unwrap()
+                .iter()
+                .all(|p| p.matches(&target, now))
+        };
+
+        assert!(matches("older:60d larger:2G under:archive name:bar clean"));
+        assert!(!matches("older:100d"));
+        assert!(!matches("larger:4G"));
+        assert!(!matches("under:/dev/archive/b"));
+        assert!(!matches("name:baz"));
+        assert!(!matches("clean name:baz"));
+        assert!(matches("match:foo-* match:/dev/**/target exclude:*/tmp/*"));
+        assert!(!matches("exclude:foo-*"));
+        assert!(!matches("exclude:/dev/archive/**"));
+    }
+}
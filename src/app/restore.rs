@@ -0,0 +1,245 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::Result;
+
+use crate::{
+    backends::{
+        quarantine::{self, QuarantinedEntry},
+        trash::{self, TrashedEntry},
+        DeleteBackend,
+    },
+    ui::components::{
+        list_with_state::ListWithState,
+        message::{Message, MessageType},
+    },
+    utils::{bytes_len_to_string_prefix, sharable_state::SharableState},
+};
+
+use super::AppState;
+
+/// A target dir removed by rtkill that can still be put back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Restorable {
+    Quarantined(QuarantinedEntry),
+    Trashed(TrashedEntry),
+}
+
+impl Restorable {
+    pub fn original_path(&self) -> &Path {
+        match self {
+            Restorable::Quarantined(entry) => &entry.original_path,
+            Restorable::Trashed(entry) => &entry.original_path,
+        }
+    }
+
+    fn restore(&self) -> Result<()> {
+        match self {
+            Restorable::Quarantined(entry) => quarantine::restore(entry),
+            Restorable::Trashed(entry) => trash::restore(entry),
+        }
+    }
+}
+
+/// Lists the target dirs rtkill moved to the quarantine or to the trash, it replaces the list while opened
+pub struct RestoreView {
+    pub entries: ListWithState<Restorable>,
+}
+
+impl SharableState<AppState> {
+    /// list the quarantined targets (last quarantined first), followed by the trashed ones
+    pub fn open_restore_view(&self) {
+        let entries = quarantine::quarantined()
+            .into_iter()
+            .map(Restorable::Quarantined)
+            .chain(
+                trash::trashed_by_rtkill()
+                    .into_iter()
+                    .map(Restorable::Trashed),
+            )
+            .collect();
+        self.mutate(|data| {
+            data.restore = Some(RestoreView {
+                entries: ListWithState {
+                    index: 0,
+                    datas: entries,
+                },
+            })
+        });
+    }
+
+    pub fn close_restore_view(&self) {
+        self.mutate(|data| data.restore = None)
+    }
+
+    pub fn restore_next(&self) {
+        self.mutate(|data| data.restore.iter_mut().for_each(|r| r.entries.next()))
+    }
+
+    pub fn restore_previous(&self) {
+        self.mutate(|data| data.restore.iter_mut().for_each(|r| r.entries.previous()))
+    }
+
+    fn selected_restorable(&self) -> Option<Restorable> {
+        match &self.read().restore {
+            Some(view) if !view.entries.datas.is_empty() => Some(view.entries.current().clone()),
+            _ => None,
+        }
+    }
+
+    fn forget_restorable(&self, entry: &Restorable) {
+        self.mutate(|data| {
+            if let Some(view) = data.restore.as_mut() {
+                view.entries.datas.retain(|e| e != entry);
+                view.entries.index = view
+                    .entries
+                    .index
+                    .min(view.entries.datas.len().saturating_sub(1));
+            }
+        });
+    }
+
+    /// put the selected entry back where it was
+    pub fn restore_selected(&self) {
+        if let Some(entry) = self.selected_restorable() {
+            self.restore(entry);
+        }
+    }
+
+    /// put back the last target moved to the quarantine
+    pub fn undo_last_quarantine(&self) {
+        match quarantine::quarantined().into_iter().next() {
+            Some(entry) => self.restore(Restorable::Quarantined(entry)),
+            None => self.set_message(Some(Message::new(
+                "Nothing to undo, the quarantine is empty",
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            ))),
+        }
+    }
+
+    /// its target is then shown as not deleted anymore
+    fn restore(&self, entry: Restorable) {
+        if let Err(e) = entry.restore() {
+            return self.set_message(Some(Message::new(
+                &e.to_string(),
+                MessageType::Error,
+                None,
+                None,
+            )));
+        }
+
+        self.forget_restorable(&entry);
+        let original_path = entry.original_path().to_path_buf();
+        self.mutate(|data| {
+            for target in data.target_directories.datas.iter_mut() {
+                if Path::new(&target.path).canonicalize().ok() == Some(original_path.clone()) {
+                    target.is_deleted = false;
+                }
+            }
+            data.update_total_size();
+        });
+        self.set_message(Some(Message::new(
+            &format!("Restored {}", original_path.display()),
+            MessageType::Success,
+            Some(Duration::from_secs(3)),
+            None,
+        )));
+    }
+
+    /// delete the selected quarantined target now, instead of waiting for the end of its grace period
+    pub fn purge_selected(&self) {
+        let entry = match self.selected_restorable() {
+            Some(Restorable::Quarantined(entry)) => entry,
+            Some(Restorable::Trashed(_)) => {
+                return self.set_message(Some(Message::new(
+                    "Trashed targets are purged by emptying the trash",
+                    MessageType::Info,
+                    Some(Duration::from_secs(3)),
+                    None,
+                )))
+            }
+            None => return,
+        };
+
+        self.set_message(Some(Message::new(
+            "Purging ⏳",
+            MessageType::Info,
+            None,
+            None,
+        )));
+        match quarantine::purge(&entry) {
+            Ok(freed) => {
+                self.record_reclaimed(
+                    format!(
+                        "{} purged from the quarantine",
+                        entry.original_path.display()
+                    ),
+                    freed,
+                );
+                self.forget_restorable(&Restorable::Quarantined(entry));
+                self.set_message(Some(Message::new(
+                    &format!("Purged {}", bytes_len_to_string_prefix(freed)),
+                    MessageType::Success,
+                    Some(Duration::from_secs(3)),
+                    None,
+                )))
+            }
+            Err(e) => self.set_message(Some(Message::new(
+                &e.to_string(),
+                MessageType::Error,
+                None,
+                None,
+            ))),
+        }
+    }
+
+    /// purge the quarantined targets whose grace period is over, it runs at startup
+    pub fn purge_expired_quarantine(&self) {
+        let (purged, freed) = quarantine::purge_expired();
+        if purged > 0 {
+            self.record_reclaimed(
+                format!("{purged} quarantined targets purged at the end of their grace period"),
+                freed,
+            );
+            self.set_message(Some(Message::new(
+                &format!(
+                    "Purged {purged} quarantined targets ({})",
+                    bytes_len_to_string_prefix(freed)
+                ),
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            )));
+        }
+    }
+
+    /// switch to the next deletion backend
+    pub fn next_backend(&self) {
+        self.mutate(|data| {
+            data.backend = match data.backend {
+                DeleteBackend::Remove => DeleteBackend::Trash,
+                DeleteBackend::Trash => DeleteBackend::Quarantine {
+                    grace_period: data.grace_period,
+                },
+                DeleteBackend::Quarantine { .. } => {
+                    DeleteBackend::CargoClean(data.clean_options.clone())
+                }
+                DeleteBackend::CargoClean(_) => DeleteBackend::Remove,
+            }
+        });
+        let outcome = self.read().backend.outcome();
+        self.set_message(Some(Message::new(
+            &format!("Deleted targets will now be {outcome}"),
+            MessageType::Info,
+            Some(Duration::from_secs(3)),
+            None,
+        )));
+    }
+}
+
+/// formats a duration as hours and minutes, e.g: "23h 59m"
+pub fn format_remaining(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
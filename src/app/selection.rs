@@ -0,0 +1,232 @@
+use std::time::Duration;
+
+use crate::{
+    config::{self, keymap::Action, project::Protection, ConfirmPolicy},
+    ui::components::{
+        message::{Message, MessageType},
+        popup::{Popup, PopupAction},
+    },
+    utils::{bytes_len_to_string_prefix, dry_run, sharable_state::SharableState},
+};
+
+use super::{AppState, TargetDir};
+
+/// number of paths listed in the deletion summary, the others are only counted
+const SUMMARY_MAX_PATHS: usize = 6;
+
+impl SharableState<AppState> {
+    /// mark the currently selected target for deletion, or unmark it, a protected target tells why it can't be
+    pub fn toggle_mark_current_item(&self) {
+        if let Some(protection) = self.current_protection() {
+            return self.set_message(Some(protection));
+        }
+        self.mutate(|data| {
+            let list = &mut data.target_directories;
+            if let Some(target) = list.datas.get_mut(list.index) {
+                target.is_marked = !target.is_marked && !target.is_deleted;
+            }
+            list.next();
+        })
+    }
+
+    /// mark all the targets still on disk, except the protected ones
+    pub fn mark_all(&self) {
+        self.mutate(|data| {
+            let ignore_protection = data.ignore_protection;
+            for target in data.target_directories.datas.iter_mut() {
+                target.is_marked = !target.is_deleted && !target.is_protected(ignore_protection);
+            }
+        })
+    }
+
+    /// mark the unmarked targets, and unmark the marked ones, the protected ones are never marked
+    pub fn invert_marks(&self) {
+        self.mutate(|data| {
+            let ignore_protection = data.ignore_protection;
+            for target in data.target_directories.datas.iter_mut() {
+                target.is_marked = !target.is_marked
+                    && !target.is_deleted
+                    && !target.is_protected(ignore_protection);
+            }
+        })
+    }
+
+    /// the message telling why the selected target can't be deleted, if it's protected
+    pub(super) fn current_protection(&self) -> Option<Message> {
+        let path = {
+            let state = self.read();
+            state
+                .target_directories
+                .datas
+                .get(state.target_directories.index)?
+                .path
+                .clone()
+        };
+        self.target_protection(&path)
+    }
+
+    /// the message telling why this target can't be deleted nor pruned, if it's protected
+    pub(super) fn target_protection(&self, path: &str) -> Option<Message> {
+        let state = self.read();
+        let target = state
+            .target_directories
+            .datas
+            .iter()
+            .find(|t| t.path == path)?;
+        if !target.is_protected(state.ignore_protection) {
+            return None;
+        }
+        let message = match &target.protection {
+            _ if target.is_pinned => format!(
+                "{} is pinned, {} to unpin it",
+                target.project_name,
+                config::get().keymap.key_name(Action::Pin)
+            ),
+            Some(Protection::Manifest(reason)) => {
+                format!("{}: {reason}, see --ignore-protection", target.project_name)
+            }
+            protection => format!(
+                "{}: {}",
+                target.project_name,
+                protection.as_ref()?.describe()
+            ),
+        };
+        Some(Message::new(
+            &message,
+            MessageType::Warning,
+            Some(Duration::from_secs(4)),
+            None,
+        ))
+    }
+
+    /// show a summary of the marked targets, they're only deleted once the user confirms it, unless the config says not to ask
+    pub fn ask_delete_marked(&self) {
+        let marked = self
+            .read()
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| t.is_marked && !t.is_deleted)
+            .cloned()
+            .collect::<Vec<_>>();
+        if marked.is_empty() {
+            self.set_message(Some(Message::new(
+                "Nothing is marked, press Space to mark a target",
+                MessageType::Info,
+                Some(Duration::from_secs(3)),
+                None,
+            )));
+            return;
+        }
+
+        let paths = marked.iter().map(|t| t.path.clone()).collect();
+        let confirm = self.read().confirm;
+        match confirm {
+            ConfirmPolicy::Always => self.set_popup(Some(Popup::new(
+                "Delete marked targets",
+                self.deletion_summary(&marked),
+                PopupAction::DeleteTargets(paths),
+            ))),
+            ConfirmPolicy::Never => self.enqueue_deletions(paths),
+        }
+    }
+
+    /// lines of the confirmation popup listing the targets about to be deleted
+    pub(super) fn deletion_summary(&self, marked: &[TargetDir]) -> Vec<String> {
+        let total_size = marked.iter().map(|t| t.size_bytes).sum::<u64>();
+        let mut lines = vec![format!(
+            "{} target directories ({}) will be {}",
+            marked.len(),
+            bytes_len_to_string_prefix(total_size),
+            self.read().backend.outcome()
+        )];
+        lines.extend(
+            marked
+                .iter()
+                .take(SUMMARY_MAX_PATHS)
+                .map(|t| format!("{}: {}", t.project_name, t.path)),
+        );
+        if marked.len() > SUMMARY_MAX_PATHS {
+            lines.push(format!("... and {} more", marked.len() - SUMMARY_MAX_PATHS));
+        }
+        let busy = marked.iter().filter(|t| t.is_busy).count();
+        if busy > 0 {
+            lines.push(format!(
+                "{busy} of them are locked by a running cargo build, they'll be deleted once it's done"
+            ));
+        }
+
+        if dry_run::is_enabled() {
+            lines.push("Dry run: nothing will actually be deleted".to_string());
+        }
+
+        lines
+    }
+}
+
+impl TargetDir {
+    /// whether a pin, its Cargo.toml or the config keeps it from being marked and deleted
+    pub fn is_protected(&self, ignore_protection: bool) -> bool {
+        self.is_pinned
+            || self
+                .protection
+                .as_ref()
+                .map(|protection| protection.applies(ignore_protection))
+                .unwrap_or(false)
+    }
+
+    /// why it can't be deleted, e.g: "pinned" or "its Cargo.toml protects it"
+    pub fn protection_reason(&self) -> Option<String> {
+        match self.is_pinned {
+            true => Some("pinned".to_string()),
+            false => self.protection.as_ref().map(|p| p.describe()),
+        }
+    }
+
+    /// text shown before the project name, so that marked targets stand out
+    pub fn mark_symbol(&self) -> &'static str {
+        match self.is_marked {
+            true => "[x] ",
+            false => "[ ] ",
+        }
+    }
+}
+
+#[cfg(test)]
+mod selection_tests {
+    use crate::{
+        app::{AppState, TargetDir},
+        utils::sharable_state::SharableState,
+    };
+
+    fn marks(state: &SharableState<AppState>) -> Vec<bool> {
+        let data = state.read();
+        data.target_directories
+            .datas
+            .iter()
+            .map(|t| t.is_marked)
+            .collect()
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut app = AppState::default();
+        app.target_directories.datas = (0..3)
+            .map(|i| TargetDir {
+                is_deleted: i == 2,
+                ..Default::default()
+            })
+            .collect();
+        let state = SharableState::new(app);
+
+        state.toggle_mark_current_item();
+        assert_eq!(marks(&state), [true, false, false]);
+        assert_eq!(state.read().target_directories.index, 1);
+
+        state.invert_marks();
+        assert_eq!(marks(&state), [false, true, false]);
+
+        state.mark_all();
+        assert_eq!(marks(&state), [true, true, false]);
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::utils::{
+    bytes_len_to_string_prefix,
+    disk::{mount_point, DiskSpace},
+    dry_run,
+    sharable_state::SharableState,
+};
+
+use super::AppState;
+
+/// Something rtkill removed during the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reclaimed {
+    /// e.g: "proj: /home/me/proj/target (deleted permanently)"
+    pub description: String,
+    /// bytes actually freed on the disk, nothing for a target moved to the trash or to the quarantine
+    pub freed: u64,
+}
+
+/// Free space of a filesystem touched during the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FilesystemSpace {
+    mount_point: String,
+    /// before the first removal on it
+    free_before: u64,
+    /// after the last removal on it
+    free_after: u64,
+}
+
+/// Space reclaimed since rtkill started, summarized when it exits
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    pub reclaimed: Vec<Reclaimed>,
+    /// by device id
+    filesystems: HashMap<u64, FilesystemSpace>,
+}
+
+impl SessionStats {
+    /// bytes freed this session
+    pub fn freed(&self) -> u64 {
+        self.reclaimed.iter().map(|r| r.freed).sum()
+    }
+
+    pub fn record(&mut self, description: String, freed: u64) {
+        self.reclaimed.push(Reclaimed { description, freed })
+    }
+
+    /// remember the free space of the filesystem around a removal, `path` is any folder on it
+    pub fn track_free_space(&mut self, path: &Path, before: DiskSpace, after: DiskSpace) {
+        if before.device != after.device {
+            return;
+        }
+        self.filesystems
+            .entry(before.device)
+            .or_insert_with(|| FilesystemSpace {
+                mount_point: mount_point(path)
+                    .map(|mount| mount.display().to_string())
+                    .unwrap_or_else(|_| path.display().to_string()),
+                free_before: before.free,
+                free_after: after.free,
+            })
+            .free_after = after.free;
+    }
+
+    /// what was removed and the total reclaimed, `None` if nothing was
+    pub fn summary(&self) -> Option<String> {
+        if self.reclaimed.is_empty() {
+            return None;
+        }
+
+        let mut summary = format!(
+            "rtkill freed {} this session:",
+            bytes_len_to_string_prefix(self.freed())
+        );
+        for reclaimed in &self.reclaimed {
+            summary.push_str(&format!(
+                "\n  {:>10}  {}",
+                bytes_len_to_string_prefix(reclaimed.freed),
+                reclaimed.description
+            ));
+        }
+        // other programs write meanwhile, so it only tells roughly what the disks gained
+        let mut filesystems = self.filesystems.values().collect::<Vec<_>>();
+        filesystems.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+        for fs in filesystems {
+            summary.push_str(&format!(
+                "\nFree space on {}: {} -> {} ({}{})",
+                fs.mount_point,
+                bytes_len_to_string_prefix(fs.free_before),
+                bytes_len_to_string_prefix(fs.free_after),
+                match fs.free_after >= fs.free_before {
+                    true => "+",
+                    false => "-",
+                },
+                bytes_len_to_string_prefix(fs.free_after.abs_diff(fs.free_before))
+            ));
+        }
+        Some(summary)
+    }
+}
+
+impl SharableState<AppState> {
+    /// add what was removed to the session stats, nothing is in a dry run
+    pub fn record_reclaimed(&self, description: String, freed: u64) {
+        if dry_run::is_enabled() {
+            return;
+        }
+        self.mutate(|data| data.session.record(description, freed))
+    }
+}
+
+impl AppState {
+    /// sum the sizes of the targets still on the disk, after a deletion or a prune changed them
+    pub fn update_total_size(&mut self) {
+        self.total_size = self
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| !t.is_deleted)
+            .map(|t| t.size_bytes)
+            .sum();
+    }
+}
+
+#[cfg(test)]
+mod session_tests {
+    use std::path::Path;
+
+    use crate::utils::disk::DiskSpace;
+
+    use super::SessionStats;
+
+    #[test]
+    fn test_session_summary() {
+        let mut session = SessionStats::default();
+        assert_eq!(session.summary(), None);
+
+        session.record("a: /a/target (deleted permanently)".to_string(), 2048);
+        session.record("b: /b/target (moved to the trash)".to_string(), 0);
+        let space = |free| DiskSpace {
+            device: 1,
+            free,
+            total: 10_000,
+        };
+        session.track_free_space(Path::new("/"), space(1000), space(2000));
+        session.track_free_space(Path::new("/"), space(2000), space(3048));
+
+        assert_eq!(session.freed(), 2048);
+        assert_eq!(
+            session.summary().unwrap(),
+            [
+                "rtkill freed 2.0 KiB this session:",
+                "     2.0 KiB  a: /a/target (deleted permanently)",
+                "          0B  b: /b/target (moved to the trash)",
+                "Free space on /: 1000B -> 3.0 KiB (+2.0 KiB)",
+            ]
+            .join("\n")
+        );
+    }
+}
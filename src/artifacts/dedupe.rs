@@ -0,0 +1,436 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, Read},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::utils::{bytes_len_to_string_prefix, dry_run};
+
+use super::profile_dirs;
+
+/// files smaller than this aren't worth linking
+const MIN_FILE_SIZE: u64 = 4096;
+
+/// How duplicated files are replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// copy-on-write clone (btrfs, xfs...), each file stays independent
+    Reflink,
+    /// hardlink to the same inode, writing in one of them changes all of them
+    Hardlink,
+}
+
+impl LinkKind {
+    /// e.g: "reflink", as the audit log records it
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinkKind::Reflink => "reflink",
+            LinkKind::Hardlink => "hardlink",
+        }
+    }
+
+    /// the other kind, the dedupe popup switches between both
+    pub fn other(&self) -> Self {
+        match self {
+            LinkKind::Reflink => LinkKind::Hardlink,
+            LinkKind::Hardlink => LinkKind::Reflink,
+        }
+    }
+}
+
+/// Files with the exact same content, on the same filesystem
+#[derive(Debug, Clone)]
+pub struct DuplicateFiles {
+    pub size: u64,
+    /// the first one is kept, the others are replaced by links to it
+    pub paths: Vec<PathBuf>,
+}
+
+/// Result of a dedupe
+#[derive(Debug, Default)]
+pub struct DedupeOutcome {
+    pub linked_files: usize,
+    pub freed: u64,
+    pub failures: usize,
+}
+
+/// List of identical artifacts found across several targets
+#[derive(Debug, Clone, Default)]
+pub struct DedupePlan {
+    pub groups: Vec<DuplicateFiles>,
+}
+
+impl DedupePlan {
+    /// find the identical files in the `deps/` and `build/` folders of the targets
+    ///
+    /// files are grouped by filesystem and size first, then by content hash, and only byte to byte equal files are kept
+    ///
+    /// the files already linked to the kept one, by a hardlink or a reflink, are left out
+    pub fn new(targets: &[PathBuf]) -> Self {
+        // (device, size) -> files, files already linked together (same inode) are only counted once
+        let mut by_size = HashMap::<(u64, u64), Vec<PathBuf>>::new();
+        let mut seen_inodes = HashSet::<(u64, u64)>::new();
+        for target in targets {
+            for profile in profile_dirs(target) {
+                for file in artifact_files(&profile) {
+                    let metadata = match fs::symlink_metadata(&file) {
+                        Ok(metadata) if metadata.is_file() && metadata.len() >= MIN_FILE_SIZE => {
+                            metadata
+                        }
+                        _ => continue,
+                    };
+                    if !seen_inodes.insert((metadata.dev(), metadata.ino())) {
+                        continue;
+                    }
+                    by_size
+                        .entry((metadata.dev(), metadata.len()))
+                        .or_default()
+                        .push(file);
+                }
+            }
+        }
+
+        let mut groups = vec![];
+        for ((_, size), files) in by_size.into_iter().filter(|(_, f)| f.len() > 1) {
+            let mut by_hash = HashMap::<u64, Vec<PathBuf>>::new();
+            for file in files {
+                if let Ok(hash) = content_hash(&file) {
+                    by_hash.entry(hash).or_default().push(file);
+                }
+            }
+
+            for mut candidates in by_hash.into_values() {
+                // a hash collision is unlikely, but a wrong link would corrupt a build
+                while candidates.len() > 1 {
+                    let keeper = candidates.remove(0);
+                    let (same, others): (Vec<_>, Vec<_>) = candidates
+                        .into_iter()
+                        .partition(|c| same_content(&keeper, c).unwrap_or(false));
+                    // the copies a previous run already reflinked share the keeper's extents, there's nothing left to free
+                    let keeper_extents = extents(&keeper);
+                    let same = same
+                        .into_iter()
+                        .filter(|c| keeper_extents.is_none() || extents(c) != keeper_extents)
+                        .collect::<Vec<_>>();
+                    if !same.is_empty() {
+                        let mut paths = vec![keeper];
+                        paths.extend(same);
+                        groups.push(DuplicateFiles { size, paths });
+                    }
+                    candidates = others;
+                }
+            }
+        }
+        groups.sort_by_key(|g| Reverse(g.reclaimable()));
+
+        Self { groups }
+    }
+
+    /// number of bytes linking the duplicates would free
+    pub fn reclaimable(&self) -> u64 {
+        self.groups.iter().map(|g| g.reclaimable()).sum()
+    }
+
+    /// number of files that would be replaced by a link
+    pub fn duplicates(&self) -> usize {
+        self.groups.iter().map(|g| g.paths.len() - 1).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// replace every duplicate by a link to the first file of its group
+    ///
+    /// it stops at the first file if the filesystem doesn't support reflinks
+    pub fn execute(&self, kind: LinkKind) -> Result<DedupeOutcome> {
+        if dry_run::is_enabled() {
+            dry_run::record(format!(
+                "{} duplicated files ({}) would be replaced by {}",
+                self.duplicates(),
+                bytes_len_to_string_prefix(self.reclaimable()),
+                match kind {
+                    LinkKind::Reflink => "reflinks",
+                    LinkKind::Hardlink => "hardlinks",
+                }
+            ));
+            return Ok(DedupeOutcome {
+                linked_files: self.duplicates(),
+                freed: self.reclaimable(),
+                failures: 0,
+            });
+        }
+        let mut outcome = DedupeOutcome::default();
+        for group in &self.groups {
+            let (keeper, duplicates) = match group.paths.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+            for duplicate in duplicates {
+                match replace_with_link(keeper, duplicate, kind) {
+                    Ok(()) => {
+                        outcome.linked_files += 1;
+                        outcome.freed += group.size;
+                    }
+                    Err(e) if kind == LinkKind::Reflink && outcome.linked_files == 0 => {
+                        return Err(anyhow!(
+                            "reflinks aren't supported on this filesystem ({e}), hardlinks can be used instead"
+                        ))
+                    }
+                    Err(_) => outcome.failures += 1,
+                }
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+impl DuplicateFiles {
+    pub fn reclaimable(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// every file of `deps/`, and of the folders of `build/`
+fn artifact_files(profile: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut dirs = vec![profile.join("deps"), profile.join("build")];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => dirs.push(entry.path()),
+                Ok(t) if t.is_file() => files.push(entry.path()),
+                _ => (),
+            }
+        }
+    }
+    files
+}
+
+fn content_hash(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let len = file.read(&mut buffer)?;
+        if len == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buffer[..len]);
+    }
+}
+
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut file_a, mut file_b) = (File::open(a)?, File::open(b)?);
+    let (mut buffer_a, mut buffer_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let len = file_a.read(&mut buffer_a)?;
+        if len == 0 {
+            return Ok(file_b.read(&mut buffer_b)? == 0);
+        }
+        file_b.read_exact(&mut buffer_b[..len])?;
+        if buffer_a[..len] != buffer_b[..len] {
+            return Ok(false);
+        }
+    }
+}
+
+/// the link is first created next to the duplicate then renamed over it, so the duplicate is never missing
+fn replace_with_link(keeper: &Path, duplicate: &Path, kind: LinkKind) -> Result<()> {
+    let file_name = duplicate
+        .file_name()
+        .ok_or(anyhow!("no file name"))?
+        .to_string_lossy();
+    let temp = duplicate.with_file_name(format!(".{file_name}.rtkill-dedupe"));
+
+    let linked = match kind {
+        LinkKind::Reflink => reflink(keeper, &temp),
+        LinkKind::Hardlink => fs::hard_link(keeper, &temp).map_err(|e| e.into()),
+    };
+    if let Err(e) = linked.and_then(|_| {
+        let permissions = fs::metadata(duplicate)?.permissions();
+        if kind == LinkKind::Reflink {
+            fs::set_permissions(&temp, permissions)?;
+        }
+        fs::rename(&temp, duplicate)?;
+        Ok(())
+    }) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, destination: &Path) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = File::open(source)?;
+    let destination = File::create(destination)?;
+    // SAFETY: both file descriptors are valid while the files are in scope
+    match unsafe { libc::ioctl(destination.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error().into()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_: &Path, _: &Path) -> Result<()> {
+    Err(anyhow!("reflinks are only supported on linux"))
+}
+
+/// most of the artifacts are a handful of extents, a file with more isn't compared
+#[cfg(target_os = "linux")]
+const MAX_EXTENTS: usize = 32;
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FiemapExtent {
+    logical: u64,
+    physical: u64,
+    length: u64,
+    reserved64: [u64; 2],
+    flags: u32,
+    reserved: [u32; 3],
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Default)]
+struct Fiemap {
+    start: u64,
+    length: u64,
+    flags: u32,
+    mapped_extents: u32,
+    extent_count: u32,
+    reserved: u32,
+    extents: [FiemapExtent; MAX_EXTENTS],
+}
+
+/// where the data of the file is on the disk, as (physical offset, length) of each extent, `None` if it can't be told
+///
+/// two files with the same extents are reflinks of each other, linking them again wouldn't free anything
+#[cfg(target_os = "linux")]
+fn extents(path: &Path) -> Option<Vec<(u64, u64)>> {
+    use std::os::unix::io::AsRawFd;
+
+    // _IOWR('f', 11, struct fiemap)
+    const FS_IOC_FIEMAP: libc::c_ulong = 0xC020_660B;
+    const FIEMAP_FLAG_SYNC: u32 = 0x1;
+    const FIEMAP_EXTENT_LAST: u32 = 0x1;
+    // the extent isn't on the disk yet, or isn't addressable by itself
+    const FIEMAP_EXTENT_UNRELIABLE: u32 = 0x2 | 0x4 | 0x200;
+
+    let file = File::open(path).ok()?;
+    let mut fiemap = Fiemap {
+        length: u64::MAX,
+        flags: FIEMAP_FLAG_SYNC,
+        extent_count: MAX_EXTENTS as u32,
+        ..Default::default()
+    };
+    // SAFETY: the file descriptor is valid while the file is in scope, and `fiemap` has room for `extent_count` extents
+    if unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut fiemap) } != 0 {
+        return None;
+    }
+    let extents = fiemap.extents.get(..fiemap.mapped_extents as usize)?;
+    let complete = extents
+        .last()
+        .map(|extent| extent.flags & FIEMAP_EXTENT_LAST != 0)
+        .unwrap_or(false);
+    if !complete
+        || extents
+            .iter()
+            .any(|extent| extent.flags & FIEMAP_EXTENT_UNRELIABLE != 0)
+    {
+        return None;
+    }
+    Some(
+        extents
+            .iter()
+            .map(|extent| (extent.physical, extent.length))
+            .collect(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn extents(_: &Path) -> Option<Vec<(u64, u64)>> {
+    None
+}
+
+#[cfg(test)]
+mod dedupe_tests {
+    use std::{fs, os::unix::fs::MetadataExt};
+
+    use super::{DedupePlan, LinkKind, MIN_FILE_SIZE};
+
+    #[test]
+    fn test_dedupe_hardlinks() {
+        let root = std::env::temp_dir().join(format!("rtkill-dedupe-{}", std::process::id()));
+        let content = vec![7u8; MIN_FILE_SIZE as usize];
+        let mut different = content.clone();
+        different[0] = 8;
+        for (target, file, content) in [
+            ("a", "libsyn-0000000000000001.rlib", &content),
+            ("b", "libsyn-0000000000000001.rlib", &content),
+            ("c", "libsyn-0000000000000001.rlib", &different),
+        ] {
+            let deps = root.join(target).join("debug/deps");
+            fs::create_dir_all(&deps).unwrap();
+            fs::create_dir_all(root.join(target).join("debug/.fingerprint")).unwrap();
+            fs::write(deps.join(file), content).unwrap();
+        }
+
+        let targets = ["a", "b", "c"].map(|t| root.join(t));
+        let plan = DedupePlan::new(&targets);
+        assert_eq!(plan.duplicates(), 1);
+        assert_eq!(plan.reclaimable(), MIN_FILE_SIZE);
+
+        let outcome = plan.execute(LinkKind::Hardlink).unwrap();
+        assert_eq!((outcome.linked_files, outcome.failures), (1, 0));
+        let inode = |t: &str| {
+            fs::metadata(root.join(t).join("debug/deps/libsyn-0000000000000001.rlib"))
+                .unwrap()
+                .ino()
+        };
+        assert_eq!(inode("a"), inode("b"));
+        assert!(DedupePlan::new(&targets).is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_dedupe_reflinks_twice() {
+        let root = std::env::temp_dir().join(format!("rtkill-reflinks-{}", std::process::id()));
+        for target in ["a", "b"] {
+            let deps = root.join(target).join("debug/deps");
+            fs::create_dir_all(&deps).unwrap();
+            fs::create_dir_all(root.join(target).join("debug/.fingerprint")).unwrap();
+            fs::write(
+                deps.join("libsyn-0000000000000001.rlib"),
+                vec![7u8; MIN_FILE_SIZE as usize * 2],
+            )
+            .unwrap();
+        }
+        let targets = ["a", "b"].map(|t| root.join(t));
+        let plan = DedupePlan::new(&targets);
+        assert_eq!(plan.reclaimable(), MIN_FILE_SIZE * 2);
+
+        // only filesystems with reflinks (btrfs, xfs...) can run it
+        if plan.execute(LinkKind::Reflink).is_ok() {
+            // the files now share their extents, a second run has nothing left to free
+            assert_eq!(DedupePlan::new(&targets).reclaimable(), 0);
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+}
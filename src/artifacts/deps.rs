@@ -0,0 +1,146 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{artifact_hash, split_name_hash};
+
+/// Space used by one dependency inside a target, summed over all its compiled variants
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyUsage {
+    /// crate name, e.g: "serde_json"
+    pub name: String,
+    pub size: u64,
+    /// number of distinct hashes the crate was compiled with (features, profiles or versions churn)
+    pub variants: usize,
+}
+
+/// Describes an entry of a `deps/` or `build/` folder
+pub struct ArtifactEntry<'a> {
+    pub file_name: &'a str,
+    pub size: u64,
+    /// the entry is a folder of `build/`, holding a build script or its output
+    pub is_build: bool,
+}
+
+/// group the artifacts of `deps/` and `build/` folders by crate, sorted by size (biggest first)
+///
+/// variants are only counted from `deps/`, since each build script has two more hashes of its own (the compiled script, and its run)
+pub fn dependencies_usage<'a>(
+    entries: impl IntoIterator<Item = ArtifactEntry<'a>>,
+) -> Vec<DependencyUsage> {
+    let mut by_crate = HashMap::<String, (u64, HashSet<&str>)>::new();
+    for entry in entries {
+        let parsed = match entry.is_build {
+            true => split_name_hash(entry.file_name),
+            false => parse_artifact_name(entry.file_name),
+        };
+        let (name, hash) = match parsed {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+
+        // build dirs are named after the package ("proc-macro2"), artifacts after the crate ("proc_macro2")
+        let usage = by_crate.entry(name.replace('-', "_")).or_default();
+        usage.0 += entry.size;
+        if !entry.is_build {
+            usage.1.insert(hash);
+        }
+    }
+
+    let mut usages = by_crate
+        .into_iter()
+        .map(|(name, (size, hashes))| DependencyUsage {
+            name,
+            size,
+            variants: hashes.len(),
+        })
+        .collect::<Vec<_>>();
+    usages.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    usages
+}
+
+/// split a `deps/` artifact name into its crate name and hash
+///
+/// e.g:
+/// ```text
+/// libserde_json-8f4a1c2b3d4e5f60.rlib -> serde_json, 8f4a1c2b3d4e5f60
+/// serde_json-8f4a1c2b3d4e5f60.d       -> serde_json, 8f4a1c2b3d4e5f60
+/// ```
+pub fn parse_artifact_name(file_name: &str) -> Option<(&str, &str)> {
+    let hash = artifact_hash(file_name)?;
+    let stem = &file_name[..file_name.rfind(hash)? - 1];
+
+    // only libraries get the "lib" prefix, binaries and dep-info files don't
+    let is_lib = [".rlib", ".rmeta", ".so", ".dylib", ".a"]
+        .iter()
+        .any(|ext| file_name.ends_with(ext));
+    let name = match is_lib {
+        true => stem.strip_prefix("lib").unwrap_or(stem),
+        false => stem,
+    };
+    Some((name, hash))
+}
+
+#[cfg(test)]
+mod deps_tests {
+    use super::{dependencies_usage, parse_artifact_name, ArtifactEntry, DependencyUsage};
+
+    #[test]
+    fn test_parse_artifact_name() {
+        assert_eq!(
+            parse_artifact_name("libserde-0123456789abcdef.rlib"),
+            Some(("serde", "0123456789abcdef"))
+        );
+        assert_eq!(
+            parse_artifact_name("liblibc-0123456789abcdef.rmeta"),
+            Some(("libc", "0123456789abcdef"))
+        );
+        assert_eq!(
+            parse_artifact_name("libc-0123456789abcdef.d"),
+            Some(("libc", "0123456789abcdef"))
+        );
+        assert_eq!(
+            parse_artifact_name("rtkill-0123456789abcdef"),
+            Some(("rtkill", "0123456789abcdef"))
+        );
+        assert_eq!(parse_artifact_name("rtkill.d"), None);
+    }
+
+    #[test]
+    fn test_dependencies_usage() {
+        let entries = [
+            ("libsyn-0000000000000001.rlib", 10, false),
+            ("libsyn-0000000000000001.rmeta", 5, false),
+            ("syn-0000000000000001.d", 1, false),
+            ("libsyn-0000000000000002.rlib", 10, false),
+            ("proc-macro2-00000000000000aa", 3, true),
+            ("proc-macro2-00000000000000bb", 3, true),
+            ("libproc_macro2-0000000000000003.rlib", 4, false),
+            ("not-an-artifact", 100, false),
+        ];
+        let usages =
+            dependencies_usage(
+                entries
+                    .iter()
+                    .map(|(file_name, size, is_build)| ArtifactEntry {
+                        file_name,
+                        size: *size,
+                        is_build: *is_build,
+                    }),
+            );
+
+        assert_eq!(
+            usages,
+            vec![
+                DependencyUsage {
+                    name: "syn".to_string(),
+                    size: 26,
+                    variants: 2
+                },
+                DependencyUsage {
+                    name: "proc_macro2".to_string(),
+                    size: 10,
+                    variants: 1
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use super::{artifact_hash, profile_dirs};
+
+/// Describes a registry crate compiled in a target's `deps/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateBuild {
+    /// package name, e.g: "proc-macro2"
+    pub name: String,
+    pub version: String,
+    /// metadata hash of the build, two projects building the same crate with the same features, profile and dependencies get the same one
+    pub hash: String,
+    pub size: u64,
+}
+
+/// Describes a crate version compiled in several targets
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateCrate {
+    pub name: String,
+    pub version: String,
+    /// number of targets holding at least one build of it
+    pub targets: usize,
+    /// number of builds of it, across all targets
+    pub builds: usize,
+    pub total_size: u64,
+    /// bytes that a shared target dir wouldn't hold, since identical builds would only be compiled once
+    pub shared_savings: u64,
+}
+
+/// Summary of all the registry crates compiled across the scanned targets
+#[derive(Debug, Clone, Default)]
+pub struct DuplicatesReport {
+    pub targets: usize,
+    /// crates versions built in more than one target, sorted by `shared_savings` (biggest first)
+    pub crates: Vec<DuplicateCrate>,
+    /// size of all the registry crates builds
+    pub total_size: u64,
+    /// what a shared `CARGO_TARGET_DIR` would save
+    pub shared_savings: u64,
+    /// number of builds that sccache could have served from its cache instead of compiling them again
+    pub cache_hits: usize,
+}
+
+impl DuplicatesReport {
+    /// aggregate the registry crates builds of every target by crate name and version
+    pub fn new(builds_by_target: &[Vec<CrateBuild>]) -> Self {
+        // (name, version) -> (targets, builds, total size, size of each distinct build)
+        type Aggregate = (usize, usize, u64, HashMap<String, u64>);
+        let mut by_version = HashMap::<(String, String), Aggregate>::new();
+
+        for builds in builds_by_target {
+            let mut seen_in_target = HashSet::new();
+            for build in builds {
+                let key = (build.name.clone(), build.version.clone());
+                let aggregate = by_version.entry(key.clone()).or_default();
+                if seen_in_target.insert(key) {
+                    aggregate.0 += 1;
+                }
+                aggregate.1 += 1;
+                aggregate.2 += build.size;
+                aggregate.3.entry(build.hash.clone()).or_insert(build.size);
+            }
+        }
+
+        let mut report = Self {
+            targets: builds_by_target.len(),
+            ..Default::default()
+        };
+        for ((name, version), (targets, builds, total_size, distinct)) in by_version {
+            let shared_savings = total_size - distinct.values().sum::<u64>();
+            report.total_size += total_size;
+            report.shared_savings += shared_savings;
+            report.cache_hits += builds - distinct.len();
+
+            if targets > 1 {
+                report.crates.push(DuplicateCrate {
+                    name,
+                    version,
+                    targets,
+                    builds,
+                    total_size,
+                    shared_savings,
+                });
+            }
+        }
+        report.crates.sort_by(|a, b| {
+            b.shared_savings
+                .cmp(&a.shared_savings)
+                .then_with(|| b.total_size.cmp(&a.total_size))
+        });
+        report
+    }
+}
+
+/// list the registry crates compiled in the `deps/` folders of a target
+///
+/// the crate version isn't part of the artifacts name, so it's read from the sources path recorded in their dep-info (`.d`) file
+pub fn registry_crates(target: &Path) -> Vec<CrateBuild> {
+    let mut builds = vec![];
+    for profile in profile_dirs(target) {
+        let entries = match fs::read_dir(profile.join("deps")) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).collect::<Vec<_>>(),
+            Err(_) => continue,
+        };
+
+        let mut size_by_hash = HashMap::<String, u64>::new();
+        for entry in &entries {
+            if let Some(hash) = entry.file_name().to_str().and_then(artifact_hash) {
+                *size_by_hash.entry(hash.to_string()).or_default() +=
+                    entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+
+        for entry in entries {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let hash = match file_name.strip_suffix(".d").and_then(artifact_hash) {
+                Some(hash) => hash,
+                None => continue,
+            };
+            let dep_info = match fs::read_to_string(entry.path()) {
+                Ok(dep_info) => dep_info,
+                Err(_) => continue,
+            };
+            if let Some((name, version)) = registry_package(&dep_info) {
+                builds.push(CrateBuild {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    hash: hash.to_string(),
+                    size: size_by_hash.get(hash).copied().unwrap_or(0),
+                });
+            }
+        }
+    }
+    builds
+}
+
+/// find the registry package a dep-info file was compiled from
+///
+/// e.g: "/home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/proc-macro2-1.0.66/src/lib.rs" gives ("proc-macro2", "1.0.66")
+pub fn registry_package(dep_info: &str) -> Option<(&str, &str)> {
+    let start = dep_info.find("/registry/src/")? + "/registry/src/".len();
+    let package_dir = dep_info[start..].split('/').nth(1)?;
+    split_package_version(package_dir)
+}
+
+/// split a registry package dir name, e.g: "proc-macro2-1.0.66" gives ("proc-macro2", "1.0.66")
+fn split_package_version(package_dir: &str) -> Option<(&str, &str)> {
+    package_dir.match_indices('-').find_map(|(i, _)| {
+        let version = &package_dir[i + 1..];
+        let mut numbers = version.splitn(3, '.');
+        let is_version = (0..3).all(|_| {
+            numbers
+                .next()
+                .and_then(|n| n.chars().next())
+                .map(|c| c.is_ascii_digit())
+                .unwrap_or(false)
+        });
+        is_version.then_some((&package_dir[..i], version))
+    })
+}
+
+#[cfg(test)]
+mod duplicates_tests {
+    use super::{registry_package, split_package_version, CrateBuild, DuplicatesReport};
+
+    fn build(name: &str, version: &str, hash: &str, size: u64) -> CrateBuild {
+        CrateBuild {
+            name: name.to_string(),
+            version: version.to_string(),
+            hash: hash.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_registry_package() {
+        assert_eq!(
+            registry_package("/p/target/debug/deps/proc_macro2-0123456789abcdef.d: /home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/proc-macro2-1.0.66/src/lib.rs"),
+            Some(("proc-macro2", "1.0.66"))
+        );
+        assert_eq!(
+            registry_package("/p/target/debug/deps/rtkill-0123456789abcdef.d: src/main.rs"),
+            None
+        );
+        assert_eq!(
+            split_package_version("foo-bar-1.0.0-beta.1"),
+            Some(("foo-bar", "1.0.0-beta.1"))
+        );
+        assert_eq!(split_package_version("base64-0.21"), None);
+    }
+
+    #[test]
+    fn test_duplicates_report() {
+        let report = DuplicatesReport::new(&[
+            vec![
+                build("syn", "2.0.32", "a", 10),
+                build("libc", "0.2.147", "b", 4),
+            ],
+            vec![
+                build("syn", "2.0.32", "a", 10),
+                build("syn", "2.0.32", "c", 12),
+            ],
+            vec![build("syn", "2.0.32", "a", 10)],
+        ]);
+
+        assert_eq!(report.targets, 3);
+        assert_eq!(report.total_size, 46);
+        assert_eq!(report.shared_savings, 20);
+        assert_eq!(report.cache_hits, 2);
+        assert_eq!(report.crates.len(), 1);
+        assert_eq!(report.crates[0].name, "syn");
+        assert_eq!(report.crates[0].targets, 3);
+        assert_eq!(report.crates[0].builds, 4);
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    fs::File,
+    io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+use super::sub_dirs;
+
+/// name of the file cargo locks in a profile dir while it builds in it
+const CARGO_LOCK: &str = ".cargo-lock";
+
+/// true while a cargo process (a build, or rust-analyzer running `cargo check`) holds the lock of one of the profile dirs of the target
+pub fn is_locked(target: &Path) -> bool {
+    lock_files(target).iter().any(|lock| is_file_locked(lock))
+}
+
+/// the `.cargo-lock` of every profile dir, e.g: "target/debug/.cargo-lock", "target/x86_64-unknown-linux-gnu/release/.cargo-lock"
+fn lock_files(target: &Path) -> Vec<PathBuf> {
+    sub_dirs(target)
+        .into_iter()
+        .flat_map(|dir| {
+            let mut dirs = sub_dirs(&dir);
+            dirs.push(dir);
+            dirs
+        })
+        .map(|dir| dir.join(CARGO_LOCK))
+        .filter(|lock| lock.is_file())
+        .collect()
+}
+
+/// non-blocking flock probe, the lock is released right away when the file is closed
+fn is_file_locked(path: &Path) -> bool {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    // SAFETY: the file descriptor is valid while the file is in scope
+    match unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } {
+        0 => false,
+        _ => io::Error::last_os_error().raw_os_error() == Some(libc::EWOULDBLOCK),
+    }
+}
+
+#[cfg(test)]
+mod locks_tests {
+    use std::{fs, fs::File, os::unix::io::AsRawFd};
+
+    use super::is_locked;
+
+    #[test]
+    fn test_is_locked() {
+        let target = std::env::temp_dir().join(format!("rtkill-locks-{}", std::process::id()));
+        let lock = target.join("debug/.cargo-lock");
+        fs::create_dir_all(target.join("debug")).unwrap();
+        fs::write(&lock, "").unwrap();
+        assert!(!is_locked(&target));
+
+        // flock locks belong to the open file, so this one conflicts with the probe even from the same process
+        let held = File::open(&lock).unwrap();
+        assert_eq!(
+            unsafe { libc::flock(held.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) },
+            0
+        );
+        assert!(is_locked(&target));
+
+        drop(held);
+        assert!(!is_locked(&target));
+        fs::remove_dir_all(target).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+pub mod dedupe;
+pub mod deps;
+pub mod duplicates;
+pub mod locks;
+pub mod prune;
+pub mod toolchains;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Describes one cargo compilation unit found in a profile dir (e.g: `target/debug`)
+///
+/// Cargo names every unit output after the same metadata hash: `.fingerprint/serde-<hash>/`, `deps/libserde-<hash>.rlib`, `build/serde-<hash>/`...
+///
+/// So all the files sharing this hash belong to the same unit and can be removed together
+#[derive(Debug, Clone)]
+pub struct Unit {
+    /// 16 hex digits metadata hash
+    pub hash: String,
+    pub fingerprint_dir: PathBuf,
+    /// every output of the unit (files in `deps/` and `examples/`, dirs in `build/`), the fingerprint dir excluded
+    pub outputs: Vec<PathBuf>,
+    /// size on disk of the outputs and of the fingerprint dir
+    pub size: u64,
+    /// last time cargo built or checked this unit
+    pub last_used: SystemTime,
+    /// hash of the `rustc -vV` output of the compiler that built the unit, as recorded in its fingerprint
+    pub rustc: Option<u64>,
+    /// version of the compiler that built the unit, e.g: "rustc 1.70.0 (90c541806 2023-05-31)", only known once resolved by `toolchains::resolve_rustc_versions`
+    pub rustc_version: Option<String>,
+}
+
+/// returns the profile dirs of a 'target' dir, i.e: the dirs holding a `.fingerprint` folder
+///
+/// they're either directly under the target (`target/debug`) or under a target triple (`target/x86_64-unknown-linux-gnu/release`)
+pub fn profile_dirs(target: &Path) -> Vec<PathBuf> {
+    let mut profiles = vec![];
+    for dir in sub_dirs(target) {
+        if dir.join(".fingerprint").is_dir() {
+            profiles.push(dir);
+        } else {
+            profiles.extend(
+                sub_dirs(&dir)
+                    .into_iter()
+                    .filter(|sub| sub.join(".fingerprint").is_dir()),
+            );
+        }
+    }
+    profiles
+}
+
+/// read the `.fingerprint` entries of a profile dir, and match each of them with the outputs it produced
+pub fn read_units(profile: &Path) -> Vec<Unit> {
+    let mut units = sub_dirs(&profile.join(".fingerprint"))
+        .into_iter()
+        .filter_map(|fingerprint_dir| {
+            let dir_name = fingerprint_dir.file_name()?.to_str()?.to_string();
+            let (_, hash) = split_name_hash(&dir_name)?;
+
+            let entries = fs::read_dir(&fingerprint_dir)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .collect::<Vec<_>>();
+            let last_used = entries
+                .iter()
+                .filter_map(|e| last_used(&e.metadata().ok()?))
+                .max()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let rustc = entries
+                .iter()
+                .filter(|e| {
+                    e.file_name()
+                        .to_str()
+                        .unwrap_or_default()
+                        .ends_with(".json")
+                })
+                .find_map(|e| fingerprint_rustc_hash(&fs::read_to_string(e.path()).ok()?));
+
+            Some(Unit {
+                hash: hash.to_string(),
+                size: fs_extra::dir::get_size(&fingerprint_dir).unwrap_or(0),
+                fingerprint_dir,
+                outputs: vec![],
+                last_used,
+                rustc,
+                rustc_version: None,
+            })
+        })
+        .map(|unit| (unit.hash.clone(), unit))
+        .collect::<HashMap<_, _>>();
+
+    for folder in ["deps", "examples", "build"] {
+        let entries = match fs::read_dir(profile.join(folder)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let file_name = entry.file_name();
+            let hash = match file_name.to_str().and_then(artifact_hash) {
+                Some(hash) => hash,
+                None => continue,
+            };
+            if let Some(unit) = units.get_mut(hash) {
+                unit.size += match entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                    true => fs_extra::dir::get_size(entry.path()).unwrap_or(0),
+                    false => entry.metadata().map(|m| m.len()).unwrap_or(0),
+                };
+                unit.outputs.push(entry.path());
+            }
+        }
+    }
+
+    units.into_values().collect()
+}
+
+/// extract the metadata hash from a cargo artifact name
+///
+/// e.g:
+/// ```text
+/// libserde-8f4a1c2b3d4e5f60.rlib        -> 8f4a1c2b3d4e5f60
+/// build_script_build-8f4a1c2b3d4e5f60.d -> 8f4a1c2b3d4e5f60
+/// ```
+pub fn artifact_hash(file_name: &str) -> Option<&str> {
+    file_name.match_indices('-').rev().find_map(|(i, _)| {
+        let hash = file_name.get(i + 1..i + 17)?;
+        let is_hash = hash.chars().all(|c| c.is_ascii_hexdigit());
+        let ends_here = matches!(file_name.as_bytes().get(i + 17), None | Some(b'.'));
+        (is_hash && ends_here).then_some(hash)
+    })
+}
+
+/// split a fingerprint or build dir name into the package name and its hash, e.g: "proc-macro2-0123456789abcdef" gives ("proc-macro2", "0123456789abcdef")
+pub fn split_name_hash(dir_name: &str) -> Option<(&str, &str)> {
+    let (name, hash) = dir_name.rsplit_once('-')?;
+    match hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Some((name, hash)),
+        false => None,
+    }
+}
+
+/// read the `"rustc"` hash of a fingerprint json, e.g: `{"rustc":7458672600737419911,"features":...}`
+fn fingerprint_rustc_hash(fingerprint: &str) -> Option<u64> {
+    let start = fingerprint.find("\"rustc\":")? + "\"rustc\":".len();
+    let digits = fingerprint[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    digits.parse().ok()
+}
+
+/// the most recent of the modification and access time, cargo reads the fingerprint of fresh units so the access time tells when it was last used
+fn last_used(metadata: &fs::Metadata) -> Option<SystemTime> {
+    match (metadata.modified().ok(), metadata.accessed().ok()) {
+        (Some(modified), Some(accessed)) => Some(modified.max(accessed)),
+        (modified, accessed) => modified.or(accessed),
+    }
+}
+
+fn sub_dirs(path: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|e| e.path())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod artifacts_tests {
+    use super::{artifact_hash, fingerprint_rustc_hash, split_name_hash};
+
+    #[test]
+    fn test_artifact_hash() {
+        assert_eq!(
+            artifact_hash("libserde-8f4a1c2b3d4e5f60.rlib"),
+            Some("8f4a1c2b3d4e5f60")
+        );
+        assert_eq!(
+            artifact_hash("libserde_json-8f4a1c2b3d4e5f60.rmeta"),
+            Some("8f4a1c2b3d4e5f60")
+        );
+        assert_eq!(
+            artifact_hash("proc-macro2-8f4a1c2b3d4e5f60.d"),
+            Some("8f4a1c2b3d4e5f60")
+        );
+        assert_eq!(
+            artifact_hash("rtkill-8f4a1c2b3d4e5f60"),
+            Some("8f4a1c2b3d4e5f60")
+        );
+        assert_eq!(artifact_hash("rtkill"), None);
+        assert_eq!(artifact_hash("rtkill.d"), None);
+        assert_eq!(artifact_hash("libfoo-bar.rlib"), None);
+    }
+
+    #[test]
+    fn test_split_name_hash() {
+        assert_eq!(
+            split_name_hash("proc-macro2-0123456789abcdef"),
+            Some(("proc-macro2", "0123456789abcdef"))
+        );
+        assert_eq!(split_name_hash("proc-macro2"), None);
+        assert_eq!(split_name_hash("serde-0123"), None);
+    }
+
+    #[test]
+    fn test_fingerprint_rustc_hash() {
+        assert_eq!(
+            fingerprint_rustc_hash(r#"{"rustc":7458672600737419911,"features":"[]"}"#),
+            Some(7458672600737419911)
+        );
+        assert_eq!(fingerprint_rustc_hash(r#"{"features":"[]"}"#), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+
+use crate::utils::{bytes_len_to_string_prefix, dry_run};
+
+use super::{profile_dirs, read_units, Unit};
+
+/// default age after which an unused unit is considered stale
+pub const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+
+/// Result of a prune, the units that could be removed are gone even if others couldn't be
+#[derive(Debug, Default)]
+pub struct PruneOutcome {
+    pub freed: u64,
+    /// each file left with the reason, e.g: "target/debug/deps/libfoo.rlib: Permission denied (os error 13)"
+    pub failures: Vec<String>,
+}
+
+/// List of units that can be removed from a 'target' dir without touching the rest of it
+#[derive(Debug, Clone, Default)]
+pub struct PrunePlan {
+    pub target: PathBuf,
+    pub units: Vec<Unit>,
+}
+
+impl PrunePlan {
+    /// find all the units of the target that weren't built or checked since `max_age`, like `cargo sweep --time` does
+    pub fn stale_units(target: &Path, max_age: Duration) -> Self {
+        let limit = SystemTime::now()
+            .checked_sub(max_age)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let units = profile_dirs(target)
+            .iter()
+            .flat_map(|profile| read_units(profile))
+            .filter(|unit| unit.last_used < limit)
+            .collect();
+
+        Self {
+            target: target.to_path_buf(),
+            units,
+        }
+    }
+
+    /// leave out the units of the folders its Cargo.toml keeps, e.g: "release"
+    pub fn without_kept(mut self, keep: &[String]) -> Self {
+        let kept = keep
+            .iter()
+            .map(|folder| self.target.join(folder))
+            .collect::<Vec<_>>();
+        self.units
+            .retain(|unit| !kept.iter().any(|dir| unit.fingerprint_dir.starts_with(dir)));
+        self
+    }
+
+    /// number of bytes this plan would free
+    pub fn size(&self) -> u64 {
+        self.units.iter().map(|unit| unit.size).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// removes the outputs of every unit and their fingerprint, so that cargo rebuilds them if they're ever needed again
+    ///
+    /// it carries on after a unit that can't be removed, the failures are listed in the outcome
+    pub fn execute(&self) -> PruneOutcome {
+        if dry_run::is_enabled() {
+            dry_run::record(format!(
+                "{} units ({}) would be pruned from {}",
+                self.units.len(),
+                bytes_len_to_string_prefix(self.size()),
+                self.target.display()
+            ));
+            return PruneOutcome {
+                freed: self.size(),
+                ..Default::default()
+            };
+        }
+        let mut outcome = PruneOutcome::default();
+        for unit in &self.units {
+            // the fingerprint goes first, cargo rebuilds a unit without one even if some of its outputs are left
+            if let Err(e) = remove_path(&unit.fingerprint_dir) {
+                outcome
+                    .failures
+                    .push(format!("{}: {e}", unit.fingerprint_dir.display()));
+                continue;
+            }
+            let failures = unit
+                .outputs
+                .iter()
+                .filter_map(|output| {
+                    remove_path(output)
+                        .err()
+                        .map(|e| format!("{}: {e}", output.display()))
+                })
+                .collect::<Vec<_>>();
+            if failures.is_empty() {
+                outcome.freed += unit.size;
+            }
+            outcome.failures.extend(failures);
+        }
+        outcome
+    }
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()), // already gone
+    };
+    match metadata.is_dir() {
+        true => fs::remove_dir_all(path)?,
+        false => fs::remove_file(path)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod prune_tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use crate::artifacts::Unit;
+
+    use super::PrunePlan;
+
+    fn unit(fingerprint_dir: &str) -> Unit {
+        Unit {
+            hash: "0123456789abcdef".to_string(),
+            fingerprint_dir: PathBuf::from(fingerprint_dir),
+            outputs: vec![],
+            size: 10,
+            last_used: SystemTime::UNIX_EPOCH,
+            rustc: None,
+            rustc_version: None,
+        }
+    }
+
+    #[test]
+    fn test_without_kept() {
+        let plan = PrunePlan {
+            target: PathBuf::from("/p/target"),
+            units: vec![
+                unit("/p/target/debug/.fingerprint/a-0123456789abcdef"),
+                unit("/p/target/release/.fingerprint/a-0123456789abcdef"),
+                unit("/p/target/x86_64-unknown-linux-gnu/release/.fingerprint/a-0123456789abcdef"),
+            ],
+        }
+        .without_kept(&["release".to_string()]);
+        assert_eq!(plan.units.len(), 2);
+        assert_eq!(plan.size(), 20);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use lazy_static::lazy_static;
+
+use super::{profile_dirs, prune::PrunePlan, read_units, Unit};
+
+lazy_static! {
+    /// versions of every rustc installed on this machine, computed once per session
+    pub static ref INSTALLED_RUSTC: HashSet<String> = installed_rustc_versions();
+}
+
+impl PrunePlan {
+    /// find all the units of the target that were built by a rustc that isn't installed anymore
+    ///
+    /// units whose compiler can't be identified are always kept
+    pub fn outdated_toolchain_units(target: &Path, installed: &HashSet<String>) -> Self {
+        // without any known compiler, everything would look outdated
+        if installed.is_empty() {
+            return Self {
+                target: target.to_path_buf(),
+                units: vec![],
+            };
+        }
+
+        let units = profile_dirs(target)
+            .iter()
+            .flat_map(|profile| {
+                let mut units = read_units(profile);
+                resolve_rustc_versions(&mut units, target);
+                units
+            })
+            .filter(|unit| match &unit.rustc_version {
+                Some(version) => !installed.contains(version),
+                None => false,
+            })
+            .collect();
+
+        Self {
+            target: target.to_path_buf(),
+            units,
+        }
+    }
+
+    /// the distinct compiler versions that built the units of this plan
+    pub fn rustc_versions(&self) -> Vec<String> {
+        let mut versions = self
+            .units
+            .iter()
+            .filter_map(|unit| unit.rustc_version.clone())
+            .collect::<Vec<_>>();
+        versions.sort();
+        versions.dedup();
+        versions
+    }
+}
+
+/// fill the `rustc_version` of each unit
+///
+/// cargo only records a hash of the compiler version in the fingerprints, so the hash is matched with the version written in the header of the `.rmeta`/`.rlib` the unit produced,
+/// or for the most recently built units, with the compiler recorded in the target `.rustc_info.json`
+pub fn resolve_rustc_versions(units: &mut [Unit], target: &Path) {
+    let mut versions_by_hash = HashMap::<u64, String>::new();
+    for unit in units.iter() {
+        let hash = match unit.rustc {
+            Some(hash) if !versions_by_hash.contains_key(&hash) => hash,
+            _ => continue,
+        };
+        if let Some(version) = unit
+            .outputs
+            .iter()
+            .filter(
+                |output| matches!(output.extension(), Some(ext) if ext == "rmeta" || ext == "rlib"),
+            )
+            .find_map(|output| metadata_rustc_version(output))
+        {
+            versions_by_hash.insert(hash, version);
+        }
+    }
+
+    if let (Some(latest), Some(version)) = (
+        units.iter().max_by_key(|unit| unit.last_used),
+        last_rustc_version(target),
+    ) {
+        if let Some(hash) = latest.rustc {
+            versions_by_hash.entry(hash).or_insert(version);
+        }
+    }
+
+    for unit in units.iter_mut() {
+        unit.rustc_version = unit
+            .rustc
+            .and_then(|hash| versions_by_hash.get(&hash).cloned());
+    }
+}
+
+/// read the version of the compiler that produced a crate metadata file (`.rmeta` or `.rlib`)
+///
+/// rustc writes it near the start of the file, e.g: "rust\0\0\0...#rustc 1.70.0 (90c541806 2023-05-31)"
+pub fn metadata_rustc_version(path: &Path) -> Option<String> {
+    let mut header = vec![0; 4096];
+    let len = File::open(path).ok()?.read(&mut header).ok()?;
+    parse_rustc_version(&header[..len])
+}
+
+fn parse_rustc_version(bytes: &[u8]) -> Option<String> {
+    let start = bytes.windows(6).position(|w| w == b"rustc ")?;
+    let end = start + bytes[start..].iter().position(|b| *b == b')')?;
+    String::from_utf8(bytes[start..=end].to_vec()).ok()
+}
+
+/// the compiler cargo last used in this target, read from the `rustc -vV` output cached in `.rustc_info.json`
+pub fn last_rustc_version(target: &Path) -> Option<String> {
+    let rustc_info = fs::read_to_string(target.join(".rustc_info.json")).ok()?;
+    let start = rustc_info.find("\"rustc ")? + 1;
+    let end = start + rustc_info[start..].find(['\\', '"'])?;
+    Some(rustc_info[start..end].to_string())
+}
+
+/// `rustc -V` of every rustup toolchain, and of the rustc in the PATH
+fn installed_rustc_versions() -> HashSet<String> {
+    let rustup_home = env::var("RUSTUP_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| Path::new(&home).join(".rustup")));
+
+    let mut compilers = vec![PathBuf::from("rustc")];
+    if let Ok(toolchains) = rustup_home.map(|home| home.join("toolchains")) {
+        if let Ok(entries) = fs::read_dir(toolchains) {
+            compilers.extend(
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path().join("bin").join("rustc")),
+            );
+        }
+    }
+
+    compilers
+        .iter()
+        .filter_map(|rustc| Command::new(rustc).arg("-V").output().ok())
+        .filter(|output| output.status.success())
+        .filter_map(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod toolchains_tests {
+    use super::parse_rustc_version;
+
+    #[test]
+    fn test_parse_rustc_version() {
+        assert_eq!(
+            parse_rustc_version(
+                b"rust\0\0\0\x08\0\0\0\0#rustc 1.70.0 (90c541806 2023-05-31)\x02\xff"
+            ),
+            Some("rustc 1.70.0 (90c541806 2023-05-31)".to_string())
+        );
+        assert_eq!(parse_rustc_version(b"rust\0\0\0\x08garbage"), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    ffi::CStr,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    mem::MaybeUninit,
+    path::PathBuf,
+    ptr,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{dry_run, state_dir};
+
+/// file of the state dir every deletion is appended to, one json object per line
+const AUDIT_LOG: &str = "audit.jsonl";
+
+/// What rtkill did to the disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// a whole target dir, or a file deleted from the explorer
+    Delete,
+    Prune,
+    Dedupe,
+    /// a quarantined target deleted for good
+    Purge,
+}
+
+/// How it went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Done,
+    /// a part of it is still on the disk, see `error`
+    Partial,
+    Failed,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// RFC 3339, in the local timezone
+    pub timestamp: String,
+    pub user: String,
+    pub action: AuditAction,
+    pub path: String,
+    pub project: String,
+    pub bytes_freed: u64,
+    /// e.g: "remove", "trash", "quarantine", "cargo clean"
+    pub backend: String,
+    pub outcome: AuditOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What the history is filtered with, every field must match
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    /// part of the path, e.g: "my-project/target"
+    pub path: Option<String>,
+    pub project: Option<String>,
+    pub since: Option<DateTime<Local>>,
+}
+
+impl AuditRecord {
+    pub fn new(action: AuditAction, path: &str, project: &str, backend: &str) -> Self {
+        Self {
+            timestamp: Local::now().to_rfc3339(),
+            user: current_user(),
+            action,
+            path: path.to_string(),
+            project: project.to_string(),
+            bytes_freed: 0,
+            backend: backend.to_string(),
+            outcome: AuditOutcome::Done,
+            error: None,
+        }
+    }
+
+    pub fn freed(mut self, bytes: u64) -> Self {
+        self.bytes_freed = bytes;
+        self
+    }
+
+    /// a record of something that didn't go as planned, `partial` when a part was removed anyway
+    pub fn failed(mut self, error: String, partial: bool) -> Self {
+        self.outcome = match partial {
+            true => AuditOutcome::Partial,
+            false => AuditOutcome::Failed,
+        };
+        self.error = Some(error);
+        self
+    }
+
+    fn matches(&self, query: &HistoryQuery) -> bool {
+        let since = match (&query.since, DateTime::parse_from_rfc3339(&self.timestamp)) {
+            (Some(since), Ok(timestamp)) => timestamp >= *since,
+            (Some(_), Err(_)) => false,
+            (None, _) => true,
+        };
+        since
+            && query
+                .path
+                .as_ref()
+                .map_or(true, |p| self.path.contains(p.as_str()))
+            && query.project.as_ref().map_or(true, |p| &self.project == p)
+    }
+}
+
+impl AuditOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditOutcome::Done => "done",
+            AuditOutcome::Partial => "partial",
+            AuditOutcome::Failed => "failed",
+        }
+    }
+}
+
+impl AuditAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditAction::Delete => "delete",
+            AuditAction::Prune => "prune",
+            AuditAction::Dedupe => "dedupe",
+            AuditAction::Purge => "purge",
+        }
+    }
+}
+
+/// append the record to the audit log, nothing is logged in a dry run since nothing happens
+pub fn log(record: &AuditRecord) -> Result<()> {
+    if dry_run::is_enabled() {
+        return Ok(());
+    }
+    let dir = state_dir()?;
+    fs::create_dir_all(&dir)?;
+    // a single write per record, so that two rtkill appending at once can't interleave their lines
+    let line = format!("{}\n", serde_json::to_string(record)?);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_log_path()?)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// the records matching the query, oldest first, the lines that can't be parsed are skipped
+pub fn history(query: &HistoryQuery) -> Result<Vec<AuditRecord>> {
+    let file = match fs::File::open(audit_log_path()?) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str::<AuditRecord>(&line).ok())
+        .filter(|record| record.matches(query))
+        .collect())
+}
+
+pub fn audit_log_path() -> Result<PathBuf> {
+    Ok(state_dir()?.join(AUDIT_LOG))
+}
+
+/// name of the real user running rtkill, from its uid rather than `$USER` which anyone can set, or the uid if it has no name
+fn current_user() -> String {
+    // SAFETY: getuid can't fail
+    let uid = unsafe { libc::getuid() };
+    let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut found = ptr::null_mut();
+    // SAFETY: the buffers outlive the call, and `pw_name` points into `buffer` which is still alive when it's read
+    unsafe {
+        libc::getpwuid_r(
+            uid,
+            passwd.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut found,
+        );
+        match found.is_null() {
+            true => uid.to_string(),
+            false => CStr::from_ptr((*found).pw_name)
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use chrono::{Duration, Local};
+
+    use super::{AuditAction, AuditOutcome, AuditRecord, HistoryQuery};
+
+    #[test]
+    fn test_record_line() {
+        let record = AuditRecord::new(AuditAction::Delete, "/dev/a/target", "a", "trash")
+            .freed(42)
+            .failed("debug/x: Permission denied".to_string(), true);
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.contains(r#""action":"delete""#));
+        assert!(line.contains(r#""outcome":"partial""#));
+        assert_eq!(serde_json::from_str::<AuditRecord>(&line).unwrap(), record);
+
+        let done = AuditRecord::new(AuditAction::Prune, "/dev/a/target", "a", "remove");
+        assert_eq!(done.outcome, AuditOutcome::Done);
+        assert!(!serde_json::to_string(&done).unwrap().contains("error"));
+    }
+
+    #[test]
+    fn test_history_query() {
+        let record = AuditRecord::new(AuditAction::Delete, "/dev/a/target", "a", "remove");
+        assert!(record.matches(&HistoryQuery::default()));
+        assert!(record.matches(&HistoryQuery {
+            path: Some("a/target".to_string()),
+            project: Some("a".to_string()),
+            since: Some(Local::now() - Duration::days(1)),
+        }));
+        assert!(!record.matches(&HistoryQuery {
+            project: Some("b".to_string()),
+            ..Default::default()
+        }));
+        assert!(!record.matches(&HistoryQuery {
+            since: Some(Local::now() + Duration::days(1)),
+            ..Default::default()
+        }));
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    io::{BufRead, BufReader, ErrorKind},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+
+use super::{remove_dir_with_progress, RemovalProgress};
+
+/// What `cargo clean` removes, everything by default
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanOptions {
+    /// `--release`, only the release artifacts
+    pub release: bool,
+    /// `--doc`, only the documentation
+    pub doc: bool,
+    /// `-p <pkg>`, only the artifacts of this package
+    pub package: Option<String>,
+}
+
+impl CleanOptions {
+    /// arguments given to `cargo clean`, after `--target-dir`
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.release {
+            args.push("--release".to_string());
+        }
+        if self.doc {
+            args.push("--doc".to_string());
+        }
+        if let Some(package) = &self.package {
+            args.extend(["-p".to_string(), package.clone()]);
+        }
+        args
+    }
+}
+
+/// run `cargo clean` from the manifest dir of the target, streaming each line cargo prints
+///
+/// the target is always given with `--target-dir`, so that a `CARGO_TARGET_DIR` set in the environment can't make cargo clean another folder. It falls back to removing the folder directly when cargo isn't on the PATH
+pub fn cargo_clean(
+    target: &Path,
+    options: &CleanOptions,
+    on_progress: &mut impl FnMut(RemovalProgress),
+) -> Result<()> {
+    let manifest_dir = target.parent().ok_or(anyhow!("no manifest dir"))?;
+    let child = Command::new("cargo")
+        .arg("clean")
+        .arg("--target-dir")
+        .arg(target)
+        .args(options.args())
+        .current_dir(manifest_dir)
+        .env("CARGO_TERM_COLOR", "never")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            on_progress(RemovalProgress::Output(
+                "cargo isn't on your PATH, removing the folder directly",
+            ));
+            return remove_dir_with_progress(target, &[], on_progress);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut last_line = String::new();
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).lines().map_while(|l| l.ok()) {
+            let line = line.trim();
+            if !line.is_empty() {
+                on_progress(RemovalProgress::Output(line));
+                last_line = line.to_string();
+            }
+        }
+    }
+
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => Err(anyhow!("cargo clean failed: {last_line}")),
+    }
+}
+
+#[cfg(test)]
+mod cargo_clean_tests {
+    use super::CleanOptions;
+
+    #[test]
+    fn test_clean_args() {
+        assert!(CleanOptions::default().args().is_empty());
+        let options = CleanOptions {
+            release: true,
+            doc: false,
+            package: Some("serde".to_string()),
+        };
+        assert_eq!(options.args(), ["--release", "-p", "serde"]);
+    }
+}
@@ -0,0 +1,261 @@
+pub mod cargo_clean;
+pub mod quarantine;
+pub mod safety;
+pub mod trash;
+
+use std::{fmt, fs, path::Path, time::Duration};
+
+use anyhow::{anyhow, Result};
+
+use crate::utils::dry_run;
+
+use self::cargo_clean::{cargo_clean, CleanOptions};
+
+/// How a target dir is removed from the disk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DeleteBackend {
+    /// deleted permanently
+    #[default]
+    Remove,
+    /// moved to the freedesktop trash, it can be restored from rtkill or from any file manager
+    Trash,
+    /// renamed into a quarantine folder on the same filesystem, and deleted permanently once the grace period is over
+    Quarantine { grace_period: Duration },
+    /// `cargo clean` ran from the project, cargo then handles its own build lock
+    CargoClean(CleanOptions),
+}
+
+/// What a backend reports while it removes a folder
+pub enum RemovalProgress<'a> {
+    /// number of files and bytes removed so far
+    Removed { files: u64, bytes: u64 },
+    /// a line printed by the command doing the removal
+    Output(&'a str),
+}
+
+/// Error of a removal that stopped partway, the files that could be removed are gone anyway
+#[derive(Debug)]
+pub struct PartialRemoval {
+    /// each file left, relative to the removed folder, with the reason, e.g: "debug/build/x: Permission denied (os error 13)"
+    pub failures: Vec<String>,
+}
+
+impl fmt::Display for PartialRemoval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} files couldn't be removed", self.failures.len())
+    }
+}
+
+impl std::error::Error for PartialRemoval {}
+
+impl DeleteBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeleteBackend::Remove => "remove",
+            DeleteBackend::Trash => "trash",
+            DeleteBackend::Quarantine { .. } => "quarantine",
+            DeleteBackend::CargoClean(_) => "cargo clean",
+        }
+    }
+
+    /// describes what happens to the removed folders, e.g: "deleted permanently"
+    pub fn outcome(&self) -> &'static str {
+        match self {
+            DeleteBackend::Remove => "deleted permanently",
+            DeleteBackend::Trash => "moved to the trash",
+            DeleteBackend::Quarantine { .. } => "moved to the quarantine",
+            DeleteBackend::CargoClean(_) => "cleaned by cargo",
+        }
+    }
+
+    /// the trash and the quarantine don't report any progress since they only rename the folder
+    ///
+    /// the folders of `keep` (e.g: "release", from the project's Cargo.toml) are left in the target, only the remove backend can do it
+    pub fn remove(
+        &self,
+        path: &Path,
+        keep: &[String],
+        mut on_progress: impl FnMut(RemovalProgress),
+    ) -> Result<()> {
+        if !keep.is_empty() && *self != DeleteBackend::Remove {
+            return Err(anyhow!(
+                "its Cargo.toml keeps {}, only the remove backend can delete the rest",
+                keep.join(", ")
+            ));
+        }
+        if dry_run::is_enabled() {
+            dry_run::record(match keep.is_empty() {
+                true => format!("{} would be {}", path.display(), self.outcome()),
+                false => format!(
+                    "{} would be {}, except {}",
+                    path.display(),
+                    self.outcome(),
+                    keep.join(", ")
+                ),
+            });
+            return Ok(());
+        }
+        match self {
+            DeleteBackend::Remove => remove_dir_with_progress(path, keep, &mut on_progress),
+            DeleteBackend::CargoClean(options) => cargo_clean(path, options, &mut on_progress),
+            DeleteBackend::Trash => trash::trash(path).map(|_| ()),
+            DeleteBackend::Quarantine { grace_period } => {
+                quarantine::quarantine(path, *grace_period).map(|_| ())
+            }
+        }
+    }
+}
+
+/// same as `fs::remove_dir_all`, but reporting the files removed as it goes
+///
+/// the walk goes through directory file descriptors, so that nothing outside of `path` can be deleted even if a folder is swapped for a symlink meanwhile
+///
+/// it carries on after a file that can't be removed, and fails with a `PartialRemoval` listing them
+///
+/// the entries of `keep` are left, and `path` with them
+///
+/// a regular file is removed on its own, e.g: one deleted from the explorer
+fn remove_dir_with_progress(
+    path: &Path,
+    keep: &[String],
+    on_progress: &mut impl FnMut(RemovalProgress),
+) -> Result<()> {
+    if fs::symlink_metadata(path)?.is_file() {
+        let bytes = safety::remove_file_at(path)?;
+        on_progress(RemovalProgress::Removed { files: 1, bytes });
+        return Ok(());
+    }
+    let (mut files, mut bytes, mut failures) = (0, 0, vec![]);
+    let dir = safety::open_dir(path)?;
+    safety::remove_dir_content_at(
+        &dir,
+        Path::new(""),
+        keep,
+        &mut |size| {
+            files += 1;
+            bytes += size;
+            on_progress(RemovalProgress::Removed { files, bytes });
+        },
+        &mut failures,
+    );
+    drop(dir);
+    if !failures.is_empty() {
+        return Err(PartialRemoval { failures }.into());
+    }
+    if keep.is_empty() {
+        fs::remove_dir(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod backends_tests {
+    use std::{
+        fs,
+        os::unix::fs::{symlink, PermissionsExt},
+    };
+
+    use super::{DeleteBackend, PartialRemoval, RemovalProgress};
+
+    #[test]
+    fn test_remove_with_progress() {
+        let root = std::env::temp_dir().join(format!("rtkill-remove-{}", std::process::id()));
+        fs::create_dir_all(root.join("debug/deps")).unwrap();
+        fs::write(root.join("debug/deps/a"), [0; 10]).unwrap();
+        fs::write(root.join("debug/b"), [0; 5]).unwrap();
+        fs::write(root.join("c"), [0; 1]).unwrap();
+
+        let mut last_progress = (0, 0);
+        DeleteBackend::Remove
+            .remove(&root, &[], |progress| {
+                if let RemovalProgress::Removed { files, bytes } = progress {
+                    last_progress = (files, bytes)
+                }
+            })
+            .unwrap();
+        assert_eq!(last_progress, (3, 16));
+        assert!(!root.exists());
+
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a"), [0; 7]).unwrap();
+        DeleteBackend::Remove
+            .remove(&root.join("a"), &[], |progress| {
+                if let RemovalProgress::Removed { files, bytes } = progress {
+                    last_progress = (files, bytes)
+                }
+            })
+            .unwrap();
+        assert_eq!(last_progress, (1, 7));
+        assert!(!root.join("a").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_remove_keeps_folders() {
+        let root = std::env::temp_dir().join(format!("rtkill-keep-{}", std::process::id()));
+        fs::create_dir_all(root.join("debug/deps")).unwrap();
+        fs::create_dir_all(root.join("release/deps")).unwrap();
+        fs::write(root.join("debug/deps/a"), [0; 10]).unwrap();
+        fs::write(root.join("release/deps/b"), [0; 5]).unwrap();
+
+        let keep = ["release".to_string()];
+        DeleteBackend::Remove.remove(&root, &keep, |_| ()).unwrap();
+        assert!(!root.join("debug").exists());
+        assert!(root.join("release/deps/b").exists());
+        // only the remove backend deletes a part of a folder
+        assert!(DeleteBackend::Trash.remove(&root, &keep, |_| ()).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_remove_doesnt_follow_symlinks() {
+        let root = std::env::temp_dir().join(format!("rtkill-symlinks-{}", std::process::id()));
+        let (target, outside) = (root.join("target"), root.join("outside"));
+        fs::create_dir_all(target.join("debug")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("keep"), [0; 4]).unwrap();
+        symlink(&outside, target.join("debug/link")).unwrap();
+
+        DeleteBackend::Remove.remove(&target, &[], |_| ()).unwrap();
+        assert!(!target.exists());
+        assert!(outside.join("keep").exists());
+
+        // the target itself swapped for a symlink
+        symlink(&outside, &target).unwrap();
+        assert!(DeleteBackend::Remove.remove(&target, &[], |_| ()).is_err());
+        assert!(outside.join("keep").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_remove_partial_failure() {
+        // root can remove files from a read-only folder
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let root = std::env::temp_dir().join(format!("rtkill-partial-{}", std::process::id()));
+        fs::create_dir_all(root.join("debug/locked")).unwrap();
+        fs::write(root.join("debug/locked/a"), [0; 10]).unwrap();
+        fs::write(root.join("debug/b"), [0; 5]).unwrap();
+        fs::set_permissions(root.join("debug/locked"), fs::Permissions::from_mode(0o555)).unwrap();
+
+        let mut freed = 0;
+        let error = DeleteBackend::Remove
+            .remove(&root, &[], |progress| {
+                if let RemovalProgress::Removed { bytes, .. } = progress {
+                    freed = bytes
+                }
+            })
+            .unwrap_err();
+        let partial = error.downcast_ref::<PartialRemoval>().unwrap();
+        assert_eq!(partial.failures.len(), 1);
+        assert!(partial.failures[0].starts_with("debug/locked/a: "));
+        assert_eq!(freed, 5);
+        assert!(root.join("debug/locked/a").exists());
+
+        fs::set_permissions(root.join("debug/locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+
+use crate::{
+    audit::{self, AuditAction, AuditRecord},
+    utils::{disk::mount_point, dry_run, state_dir},
+};
+
+use super::{
+    remove_dir_with_progress,
+    trash::{decode_path, encode_path},
+    RemovalProgress,
+};
+
+/// how long a quarantined target is kept before being purged, by default
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(24 * 3600);
+/// staging folder used when the target isn't on the same filesystem as the state dir
+const MOUNT_QUARANTINE_DIR: &str = ".rtkill-quarantine";
+/// file of the state dir listing every quarantined target, one per line: "<quarantined at>\t<purge at>\t<quarantined path>\t<original path>"
+const QUARANTINE_RECORD: &str = "quarantined";
+
+lazy_static! {
+    /// the record is rewritten on each restore and purge, which can happen from several threads
+    static ref RECORD_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Describes a target renamed into a quarantine folder, waiting for its purge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantinedEntry {
+    pub quarantined_path: PathBuf,
+    pub original_path: PathBuf,
+    /// unix timestamps, in seconds
+    pub quarantined_at: u64,
+    pub purge_at: u64,
+}
+
+/// rename `path` into the quarantine folder of its filesystem, it's purged once `grace_period` is over
+pub fn quarantine(path: &Path, grace_period: Duration) -> Result<QuarantinedEntry> {
+    let original_path = path.canonicalize()?;
+    let dir = quarantine_dir_for(&original_path)?;
+    fs::create_dir_all(&dir)?;
+
+    let now = unix_now();
+    // e.g: "1689347132-my_project-target"
+    let base_name = format!(
+        "{}-{}",
+        original_path
+            .parent()
+            .and_then(|p| p.file_name())
+            .unwrap_or_default()
+            .to_string_lossy(),
+        original_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    );
+    let quarantined_path = (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{now}-{base_name}")),
+            n => dir.join(format!("{now}-{base_name}.{n}")),
+        })
+        .find(|path| !path.exists())
+        .ok_or(anyhow!("no name available in the quarantine"))?;
+
+    fs::rename(&original_path, &quarantined_path)?;
+    let entry = QuarantinedEntry {
+        quarantined_path,
+        original_path,
+        quarantined_at: now,
+        purge_at: now + grace_period.as_secs(),
+    };
+
+    let _lock = RECORD_LOCK.lock().map_err(|_| anyhow!("poisoned lock"))?;
+    let mut entries = read_record();
+    entries.insert(0, entry.clone());
+    write_record(&entries)?;
+    Ok(entry)
+}
+
+/// every target still in quarantine, last quarantined first
+pub fn quarantined() -> Vec<QuarantinedEntry> {
+    read_record()
+        .into_iter()
+        .filter(|e| e.quarantined_path.exists())
+        .collect()
+}
+
+/// rename a quarantined target back to where it was
+pub fn restore(entry: &QuarantinedEntry) -> Result<()> {
+    if entry.original_path.exists() {
+        return Err(anyhow!(
+            "{} already exists, it can't be restored",
+            entry.original_path.display()
+        ));
+    }
+    fs::rename(&entry.quarantined_path, &entry.original_path)?;
+    forget(entry)
+}
+
+/// delete a quarantined target permanently, returns the bytes freed
+pub fn purge(entry: &QuarantinedEntry) -> Result<u64> {
+    if dry_run::is_enabled() {
+        dry_run::record(format!(
+            "{} would be purged from the quarantine",
+            entry.original_path.display()
+        ));
+        return Ok(0);
+    }
+    let project = entry
+        .original_path
+        .parent()
+        .and_then(|project| project.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let record = AuditRecord::new(
+        AuditAction::Purge,
+        &entry.original_path.to_string_lossy(),
+        &project,
+        "quarantine",
+    );
+
+    let mut freed = 0;
+    let purged = match entry.quarantined_path.exists() {
+        true => remove_dir_with_progress(&entry.quarantined_path, &[], &mut |progress| {
+            if let RemovalProgress::Removed { bytes, .. } = progress {
+                freed = bytes
+            }
+        }),
+        false => Ok(()),
+    }
+    .and_then(|_| forget(entry));
+
+    // the purge is done either way, a record that can't be written doesn't change it
+    let _ = audit::log(&match &purged {
+        Ok(()) => record.freed(freed),
+        Err(e) => record.freed(freed).failed(e.to_string(), freed > 0),
+    });
+    purged.map(|_| freed)
+}
+
+/// purge the quarantined targets whose grace period is over, returns how many were purged and the bytes freed
+pub fn purge_expired() -> (usize, u64) {
+    let now = unix_now();
+    quarantined()
+        .iter()
+        .filter(|e| e.purge_at <= now)
+        .filter_map(|e| purge(e).ok())
+        .fold((0, 0), |(purged, freed), bytes| (purged + 1, freed + bytes))
+}
+
+impl QuarantinedEntry {
+    /// time left before the purge, zero once it's due
+    pub fn purge_in(&self) -> Duration {
+        Duration::from_secs(self.purge_at.saturating_sub(unix_now()))
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.quarantined_at,
+            self.purge_at,
+            encode_path(&self.quarantined_path),
+            encode_path(&self.original_path)
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        Some(Self {
+            quarantined_at: fields.next()?.parse().ok()?,
+            purge_at: fields.next()?.parse().ok()?,
+            quarantined_path: decode_path(fields.next()?)?,
+            original_path: decode_path(fields.next()?)?,
+        })
+    }
+}
+
+/// the state dir if `path` is on the same filesystem, otherwise a `.rtkill-quarantine` folder at the top of its mount point, so that the rename is always atomic
+fn quarantine_dir_for(path: &Path) -> Result<PathBuf> {
+    let state_quarantine = state_dir()?.join("quarantine");
+    let device = fs::metadata(path)?.dev();
+    let state_device = state_quarantine
+        .ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .map(|m| m.dev());
+    match state_device == Some(device) {
+        true => Ok(state_quarantine),
+        false => Ok(mount_point(path)?.join(MOUNT_QUARANTINE_DIR)),
+    }
+}
+
+fn forget(entry: &QuarantinedEntry) -> Result<()> {
+    let _lock = RECORD_LOCK.lock().map_err(|_| anyhow!("poisoned lock"))?;
+    let entries = read_record()
+        .into_iter()
+        .filter(|e| e != entry)
+        .collect::<Vec<_>>();
+    write_record(&entries)
+}
+
+fn read_record() -> Vec<QuarantinedEntry> {
+    state_dir()
+        .and_then(|dir| Ok(fs::read_to_string(dir.join(QUARANTINE_RECORD))?))
+        .map(|record| {
+            record
+                .lines()
+                .filter_map(QuarantinedEntry::from_line)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// written aside then renamed, so that a crash can't leave a record whose targets can't be restored nor purged
+fn write_record(entries: &[QuarantinedEntry]) -> Result<()> {
+    let dir = state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let content = entries
+        .iter()
+        .map(|e| e.to_line() + "\n")
+        .collect::<String>();
+    let temporary = dir.join(format!("{QUARANTINE_RECORD}.tmp"));
+    fs::write(&temporary, content)?;
+    fs::rename(temporary, dir.join(QUARANTINE_RECORD))?;
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod quarantine_tests {
+    use std::path::PathBuf;
+
+    use super::QuarantinedEntry;
+
+    #[test]
+    fn test_record_line() {
+        let entry = QuarantinedEntry {
+            quarantined_path: PathBuf::from(
+                "/home/me/.local/state/rtkill/quarantine/1689347132-my project-target",
+            ),
+            original_path: PathBuf::from("/home/me/dev/my project/target"),
+            quarantined_at: 1689347132,
+            purge_at: 1689433532,
+        };
+        let line = entry.to_line();
+        assert_eq!(line.split('\t').count(), 4);
+        assert_eq!(QuarantinedEntry::from_line(&line), Some(entry));
+        assert_eq!(
+            QuarantinedEntry::from_line("1689347132\tnot a timestamp\t/a\t/b"),
+            None
+        );
+    }
+}
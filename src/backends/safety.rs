@@ -0,0 +1,250 @@
+use std::{
+    ffi::{CStr, CString, OsStr},
+    fs, io,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    },
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+
+use crate::{
+    artifacts::profile_dirs,
+    config::{self, project::ProjectMetadata},
+};
+
+/// first line of the `CACHEDIR.TAG` cargo writes in the targets it creates
+const CACHEDIR_TAG_SIGNATURE: &str = "Signature: 8a477f597d28d172789f06886806bc55";
+
+/// check again, right before deleting it, that `target` is still what the search found minutes ago
+///
+/// it must be a real folder (not a symlink), inside the scan root once both are canonicalized, next to a `Cargo.toml`, hold one of cargo's markers, and be outside of the folders the config protects
+///
+/// a target whose deletion already removed a part (`partially_deleted`) may have lost its markers, they're not required then
+///
+/// the `[metadata.rtkill]` of the `Cargo.toml` is returned, the target is refused if it protects it, unless `ignore_protection`
+pub fn verify_target(
+    target: &Path,
+    scan_root: &Path,
+    partially_deleted: bool,
+    ignore_protection: bool,
+) -> Result<ProjectMetadata> {
+    let metadata = fs::symlink_metadata(target)?;
+    if metadata.file_type().is_symlink() || !metadata.is_dir() {
+        return Err(anyhow!("it isn't a folder anymore"));
+    }
+
+    let (canonical_target, canonical_root) = (target.canonicalize()?, scan_root.canonicalize()?);
+    if canonical_target == canonical_root || !canonical_target.starts_with(&canonical_root) {
+        return Err(anyhow!(
+            "it resolves to {}, outside of the scanned folder",
+            canonical_target.display()
+        ));
+    }
+
+    if config::get().is_protected(&canonical_target) {
+        return Err(anyhow!("it's in a folder protected by the config"));
+    }
+
+    let has_manifest = canonical_target
+        .parent()
+        .map(|project| project.join("Cargo.toml").is_file())
+        .unwrap_or(false);
+    let cache_dir_tag = fs::read_to_string(canonical_target.join("CACHEDIR.TAG"))
+        .map(|tag| tag.starts_with(CACHEDIR_TAG_SIGNATURE))
+        .unwrap_or(false);
+    // targets built before cargo wrote `CACHEDIR.TAG` still have these
+    let has_cargo_marker = partially_deleted
+        || cache_dir_tag
+        || canonical_target.join(".rustc_info.json").is_file()
+        || !profile_dirs(&canonical_target).is_empty();
+    if !has_manifest || !has_cargo_marker {
+        return Err(anyhow!("it doesn't look like a cargo target anymore"));
+    }
+
+    let manifest = canonical_target.with_file_name("Cargo.toml");
+    let metadata = match (ProjectMetadata::read(&manifest), ignore_protection) {
+        (Ok(metadata), _) => metadata,
+        (Err(_), true) => ProjectMetadata::default(),
+        (Err(e), false) => return Err(anyhow!("its Cargo.toml can't be read: {e:#}")),
+    };
+    let last_modified: DateTime<Local> = fs::metadata(&canonical_target)?.modified()?.into();
+    match metadata.protection(last_modified, Local::now()) {
+        Some(reason) if !ignore_protection => Err(anyhow!("{reason}")),
+        _ => Ok(metadata),
+    }
+}
+
+/// removes the content of the folder `dir`, walking it with directory file descriptors (`openat`, `unlinkat`) so that a folder swapped for a symlink meanwhile is never followed
+///
+/// `on_removed` is called with the size of each removed file, a file that can't be removed doesn't stop the walk: it's added to `failures` with the reason, under its path relative to `dir`
+///
+/// the entries of `dir` named in `keep` are left as they are
+pub fn remove_dir_content_at(
+    dir: &OwnedFd,
+    relative_path: &Path,
+    keep: &[String],
+    on_removed: &mut impl FnMut(u64),
+    failures: &mut Vec<String>,
+) {
+    let names = match read_dir_names(dir.as_raw_fd()) {
+        Ok(names) => names,
+        Err(e) => return failures.push(format!("{}: {e}", relative_path.display())),
+    };
+    for name in names
+        .into_iter()
+        .filter(|name| !keep.iter().any(|kept| kept.as_bytes() == name.to_bytes()))
+    {
+        let path = relative_path.join(OsStr::from_bytes(name.to_bytes()));
+        let removed = fstatat(dir.as_raw_fd(), &name).and_then(|stat| {
+            match stat.st_mode & libc::S_IFMT == libc::S_IFDIR {
+                true => {
+                    let child = open_dir_at(dir.as_raw_fd(), &name)?;
+                    let failed_before = failures.len();
+                    remove_dir_content_at(&child, &path, &[], on_removed, failures);
+                    // the folder can't be empty, its failures are enough
+                    match failures.len() == failed_before {
+                        true => unlink_at(dir.as_raw_fd(), &name, libc::AT_REMOVEDIR),
+                        false => Ok(()),
+                    }
+                }
+                // symlinks are removed like files, never followed
+                false => {
+                    unlink_at(dir.as_raw_fd(), &name, 0)?;
+                    on_removed(stat.st_size as u64);
+                    Ok(())
+                }
+            }
+        });
+        if let Err(e) = removed {
+            failures.push(format!("{}: {e}", path.display()))
+        }
+    }
+}
+
+/// removes the regular file `path` through a file descriptor of its folder, returns its size
+pub fn remove_file_at(path: &Path) -> io::Result<u64> {
+    let (parent, name) = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => (parent, CString::new(name.as_bytes())?),
+        _ => return Err(io::Error::from(io::ErrorKind::InvalidInput)),
+    };
+    let dir = open_dir(parent)?;
+    let stat = fstatat(dir.as_raw_fd(), &name)?;
+    if stat.st_mode & libc::S_IFMT != libc::S_IFREG {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+    unlink_at(dir.as_raw_fd(), &name, 0)?;
+    Ok(stat.st_size as u64)
+}
+
+/// open a folder without following it if it's a symlink
+pub fn open_dir(path: &Path) -> io::Result<OwnedFd> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    open_dir_at(libc::AT_FDCWD, &path)
+}
+
+fn open_dir_at(dir: RawFd, name: &CStr) -> io::Result<OwnedFd> {
+    let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+    // SAFETY: `name` is a valid C string, and the returned fd is owned right away
+    match unsafe { libc::openat(dir, name.as_ptr(), flags) } {
+        -1 => Err(io::Error::last_os_error()),
+        fd => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
+    }
+}
+
+fn read_dir_names(dir: RawFd) -> io::Result<Vec<CString>> {
+    // `fdopendir` takes the ownership of the fd it's given, so it gets its own copy
+    // SAFETY: `dir` is a valid fd, and the stream is closed before returning
+    let stream = unsafe {
+        let fd = libc::fcntl(dir, libc::F_DUPFD_CLOEXEC, 0);
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let stream = libc::fdopendir(fd);
+        if stream.is_null() {
+            libc::close(fd);
+            return Err(io::Error::last_os_error());
+        }
+        libc::rewinddir(stream);
+        stream
+    };
+
+    let mut names = vec![];
+    loop {
+        // SAFETY: `stream` is valid until `closedir`, and each entry is copied before the next `readdir`
+        let entry = unsafe { libc::readdir(stream) };
+        if entry.is_null() {
+            break;
+        }
+        let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+        if name.to_bytes() != b"." && name.to_bytes() != b".." {
+            names.push(name.to_owned());
+        }
+    }
+    unsafe { libc::closedir(stream) };
+    Ok(names)
+}
+
+fn fstatat(dir: RawFd, name: &CStr) -> io::Result<libc::stat> {
+    // SAFETY: `stat` is fully written by a successful `fstatat`
+    let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+    match unsafe { libc::fstatat(dir, name.as_ptr(), &mut stat, libc::AT_SYMLINK_NOFOLLOW) } {
+        0 => Ok(stat),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn unlink_at(dir: RawFd, name: &CStr, flags: libc::c_int) -> io::Result<()> {
+    // SAFETY: `name` is a valid C string
+    match unsafe { libc::unlinkat(dir, name.as_ptr(), flags) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod safety_tests {
+    use std::{fs, os::unix::fs::symlink};
+
+    use super::verify_target;
+
+    #[test]
+    fn test_verify_target() {
+        let root = std::env::temp_dir().join(format!("rtkill-safety-{}", std::process::id()));
+        let (project, outside) = (root.join("scan/proj"), root.join("outside/proj"));
+        for dir in [&project, &outside] {
+            fs::create_dir_all(dir.join("target/debug/.fingerprint")).unwrap();
+            fs::write(dir.join("Cargo.toml"), "").unwrap();
+        }
+        let scan_root = root.join("scan");
+        assert!(verify_target(&project.join("target"), &scan_root, false, false).is_ok());
+        assert!(verify_target(&outside.join("target"), &scan_root, false, false).is_err());
+        assert!(verify_target(&scan_root, &scan_root, false, false).is_err());
+
+        // protected by its Cargo.toml
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package.metadata.rtkill]\nprotect = true",
+        )
+        .unwrap();
+        assert!(verify_target(&project.join("target"), &scan_root, false, false).is_err());
+        assert!(verify_target(&project.join("target"), &scan_root, false, true).is_ok());
+        fs::write(project.join("Cargo.toml"), "").unwrap();
+
+        // swapped for a symlink to another target
+        fs::remove_dir_all(project.join("target")).unwrap();
+        symlink(outside.join("target"), project.join("target")).unwrap();
+        assert!(verify_target(&project.join("target"), &scan_root, false, false).is_err());
+
+        // a folder that isn't a target anymore
+        fs::remove_file(project.join("target")).unwrap();
+        fs::create_dir_all(project.join("target/src")).unwrap();
+        assert!(verify_target(&project.join("target"), &scan_root, false, false).is_err());
+        assert!(verify_target(&project.join("target"), &scan_root, true, false).is_ok());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    fs::{self, DirBuilder, OpenOptions},
+    io::{ErrorKind, Write},
+    os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+
+use crate::utils::{data_home, disk::mount_point, state_dir};
+
+/// file of the state dir listing the `.trashinfo` of every folder rtkill has trashed, one per line
+const TRASHED_RECORD: &str = "trashed";
+
+/// A trash directory, as described by the [freedesktop trash spec](https://specifications.freedesktop.org/trash-spec/trashspec-latest.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashDir {
+    /// folder holding `files/` and `info/`
+    pub root: PathBuf,
+    /// mount point the trash belongs to, the paths of its `.trashinfo` are relative to it. `None` for the home trash, which uses absolute paths
+    pub top_dir: Option<PathBuf>,
+}
+
+/// Describes a folder moved to the trash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashedEntry {
+    pub info_path: PathBuf,
+    /// where the folder is now, in the `files/` of the trash
+    pub trashed_path: PathBuf,
+    pub original_path: PathBuf,
+    /// local time, e.g: "2023-07-14T17:05:32"
+    pub deletion_date: String,
+}
+
+/// move `path` to the trash of its filesystem, and remember it so that it can be restored from rtkill
+pub fn trash(path: &Path) -> Result<TrashedEntry> {
+    let path = path.canonicalize()?;
+    let entry = TrashDir::for_path(&path)?.put(&path)?;
+    record_trashed(&entry.info_path)?;
+    Ok(entry)
+}
+
+/// every folder trashed by rtkill that is still in the trash, last trashed first
+pub fn trashed_by_rtkill() -> Vec<TrashedEntry> {
+    let record = match state_dir().and_then(|dir| Ok(fs::read_to_string(dir.join(TRASHED_RECORD))?))
+    {
+        Ok(record) => record,
+        Err(_) => return vec![],
+    };
+    record
+        .lines()
+        .rev()
+        .filter_map(|info_path| TrashedEntry::read(Path::new(info_path)).ok())
+        .collect()
+}
+
+/// put a trashed folder back where it was
+pub fn restore(entry: &TrashedEntry) -> Result<()> {
+    if entry.original_path.exists() {
+        return Err(anyhow!(
+            "{} already exists, it can't be restored",
+            entry.original_path.display()
+        ));
+    }
+    if let Some(parent) = entry.original_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&entry.trashed_path, &entry.original_path)?;
+    fs::remove_file(&entry.info_path)?;
+    Ok(())
+}
+
+impl TrashDir {
+    /// the home trash if `path` is on the same filesystem, otherwise the trash at the top of its mount point
+    pub fn for_path(path: &Path) -> Result<Self> {
+        let home_trash = data_home()?.join("Trash");
+        let device = fs::metadata(path)?.dev();
+
+        // the home trash may not exist yet, its closest existing parent tells on which filesystem it'll be
+        let home_device = home_trash
+            .ancestors()
+            .find_map(|dir| fs::metadata(dir).ok())
+            .map(|m| m.dev());
+        if home_device == Some(device) {
+            return Ok(Self {
+                root: home_trash,
+                top_dir: None,
+            });
+        }
+
+        let top_dir = mount_point(path)?;
+        // SAFETY: getuid can't fail
+        let uid = unsafe { libc::getuid() };
+
+        // an admin-created `.Trash` is only used if it's a real folder with the sticky bit
+        let shared = top_dir.join(".Trash");
+        let root = match fs::symlink_metadata(&shared) {
+            Ok(m) if m.is_dir() && m.permissions().mode() & 0o1000 != 0 => {
+                shared.join(uid.to_string())
+            }
+            _ => top_dir.join(format!(".Trash-{uid}")),
+        };
+        Ok(Self {
+            root,
+            top_dir: Some(top_dir),
+        })
+    }
+
+    /// move `path` in this trash, alongside its `.trashinfo`
+    pub fn put(&self, path: &Path) -> Result<TrashedEntry> {
+        let (files, info) = (self.root.join("files"), self.root.join("info"));
+        for dir in [&files, &info] {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+
+        let original_path = match &self.top_dir {
+            Some(top_dir) => path.strip_prefix(top_dir)?,
+            None => path,
+        };
+        let deletion_date = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        let content = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={deletion_date}\n",
+            encode_path(original_path)
+        );
+
+        // the `.trashinfo` is created first and atomically, it reserves the name in `files/`
+        let base_name = path
+            .file_name()
+            .ok_or(anyhow!("no file name"))?
+            .to_string_lossy();
+        let (name, mut info_file) = (1..)
+            .map(|n| match n {
+                1 => base_name.to_string(),
+                n => format!("{base_name}.{n}"),
+            })
+            .filter(|name| !files.join(name).exists())
+            .find_map(|name| {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(info.join(format!("{name}.trashinfo")));
+                match file {
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => None,
+                    file => Some((name, file)),
+                }
+            })
+            .ok_or(anyhow!("no name available in the trash"))?;
+        let info_path = info.join(format!("{name}.trashinfo"));
+        let trashed_path = files.join(&name);
+
+        let moved = info_file
+            .as_mut()
+            .map_err(|e| anyhow!("couldn't create the trash info: {e}"))
+            .and_then(|file| Ok(file.write_all(content.as_bytes())?))
+            .and_then(|_| Ok(fs::rename(path, &trashed_path)?));
+        if let Err(e) = moved {
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+
+        Ok(TrashedEntry {
+            info_path,
+            trashed_path,
+            original_path: path.to_path_buf(),
+            deletion_date,
+        })
+    }
+}
+
+impl TrashedEntry {
+    /// parse a `.trashinfo` file, it fails if the trashed folder isn't there anymore
+    pub fn read(info_path: &Path) -> Result<Self> {
+        let trash_root = info_path
+            .parent()
+            .and_then(|info| info.parent())
+            .ok_or(anyhow!("not in a trash"))?;
+        let name = info_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".trashinfo"))
+            .ok_or(anyhow!("not a trash info"))?;
+
+        let (path, deletion_date) = parse_trash_info(&fs::read_to_string(info_path)?)
+            .ok_or(anyhow!("invalid trash info"))?;
+        // relative paths are relative to the mount point holding the trash (`$topdir/.Trash-$uid` or `$topdir/.Trash/$uid`)
+        let original_path = match path.is_absolute() {
+            true => path,
+            false => {
+                let depth = match trash_root.parent() {
+                    Some(p) if p.file_name().map(|n| n == ".Trash").unwrap_or(false) => 2,
+                    _ => 1,
+                };
+                trash_root
+                    .ancestors()
+                    .nth(depth)
+                    .ok_or(anyhow!("no top dir"))?
+                    .join(path)
+            }
+        };
+
+        let trashed_path = trash_root.join("files").join(name);
+        if !trashed_path.exists() {
+            return Err(anyhow!("{} isn't in the trash anymore", name));
+        }
+        Ok(Self {
+            info_path: info_path.to_path_buf(),
+            trashed_path,
+            original_path,
+            deletion_date,
+        })
+    }
+}
+
+/// returns the `Path` and `DeletionDate` of a `.trashinfo` content
+fn parse_trash_info(content: &str) -> Option<(PathBuf, String)> {
+    let mut lines = content.lines();
+    if lines.next()?.trim() != "[Trash Info]" {
+        return None;
+    }
+
+    let (mut path, mut deletion_date) = (None, None);
+    for line in lines {
+        match line.split_once('=') {
+            Some(("Path", value)) => path = decode_path(value.trim()),
+            Some(("DeletionDate", value)) => deletion_date = Some(value.trim().to_string()),
+            _ => (),
+        }
+    }
+    Some((path?, deletion_date?))
+}
+
+/// percent-encode a path as required by the `.trashinfo` format, only the unreserved characters and `/` are kept as is
+pub(super) fn encode_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+pub(super) fn decode_path(encoded: &str) -> Option<PathBuf> {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    let mut bytes = vec![];
+    let mut chars = encoded.bytes();
+    while let Some(b) = chars.next() {
+        match b {
+            b'%' => {
+                let hex = [chars.next()?, chars.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b => bytes.push(b),
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+fn record_trashed(info_path: &Path) -> Result<()> {
+    let dir = state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let mut record = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(TRASHED_RECORD))?;
+    writeln!(record, "{}", info_path.display())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod trash_tests {
+    use std::{fs, path::Path};
+
+    use super::{decode_path, encode_path, parse_trash_info, restore, TrashDir, TrashedEntry};
+
+    #[test]
+    fn test_trash_info() {
+        let encoded = encode_path(Path::new("/home/me/my project/target"));
+        assert_eq!(encoded, "/home/me/my%20project/target");
+        assert_eq!(
+            decode_path(&encoded).unwrap(),
+            Path::new("/home/me/my project/target")
+        );
+
+        assert_eq!(
+            parse_trash_info(
+                "[Trash Info]\nPath=dev/p%C3%A9/target\nDeletionDate=2023-07-14T17:05:32\n"
+            ),
+            Some((
+                Path::new("dev/pé/target").to_path_buf(),
+                "2023-07-14T17:05:32".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_trash_info("Path=/a\nDeletionDate=2023-07-14T17:05:32"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_trash_and_restore() {
+        let root = std::env::temp_dir().join(format!("rtkill-trash-{}", std::process::id()));
+        let target = root.join("proj/target");
+        fs::create_dir_all(target.join("debug")).unwrap();
+        let trash = TrashDir {
+            root: root.join("Trash"),
+            top_dir: None,
+        };
+
+        let first = trash.put(&target).unwrap();
+        assert!(!target.exists());
+        assert_eq!(first.trashed_path, root.join("Trash/files/target"));
+
+        // a second folder with the same name gets another one in the trash
+        fs::create_dir_all(&target).unwrap();
+        let second = trash.put(&target).unwrap();
+        assert_eq!(second.trashed_path, root.join("Trash/files/target.2"));
+
+        let read = TrashedEntry::read(&first.info_path).unwrap();
+        assert_eq!(read.original_path, target);
+        assert_eq!(read.deletion_date, first.deletion_date);
+
+        restore(&read).unwrap();
+        assert!(target.join("debug").is_dir());
+        assert!(!first.info_path.exists());
+        // the original path is taken again
+        assert!(restore(&second).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use clap::{
+    error::ErrorKind, parser::ValueSource, Args, CommandFactory, FromArgMatches, Parser,
+    Subcommand, ValueEnum,
+};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        budget::{parse_percent, Budget, BudgetPolicy},
+        predicate::{parse_age, parse_glob, Predicate},
+    },
+    artifacts::prune::DEFAULT_MAX_AGE_DAYS,
+    backends::{cargo_clean::CleanOptions, DeleteBackend},
+    config::{self, check_date_format, ConfirmPolicy, SizeUnits},
+    utils::{check_size, parse_bytes},
+};
+
+/// It deletes rust 'target' folders
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// options of the interactive app, when no command is given
+    #[command(flatten)]
+    pub tui: TuiArgs,
+    /// nothing is deleted, pruned or linked, what would have been is printed when rtkill exits
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// read this config file instead of `$XDG_CONFIG_HOME/rtkill/config.toml`
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// print the sizes in powers of 1024 (KiB) or 1000 (kB)
+    #[arg(long, global = true, value_enum)]
+    pub size_units: Option<SizeUnits>,
+}
+
+/// the `--days` options are bounded, a longer duration would overflow
+const MAX_DAYS: u64 = 100 * 366;
+
+/// options of [Cli] the commands take too, the others are the app's
+const GLOBAL_OPTIONS: [&str; 3] = ["dry_run", "config", "size_units"];
+
+impl Cli {
+    /// parse the command line, the invalid arguments exit with the error and the usage
+    pub fn parse_args() -> Self {
+        Self::try_parse_args_from(env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+
+    /// the options of the app can't be given with a command, e.g: `rtkill --instant-delete list`
+    pub fn try_parse_args_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut command = Self::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+        if let Some((name, _)) = matches.subcommand() {
+            let app_option = command.get_arguments().find(|arg| {
+                let id = arg.get_id().as_str();
+                !GLOBAL_OPTIONS.contains(&id)
+                    && matches.value_source(id) == Some(ValueSource::CommandLine)
+            });
+            if let Some(option) = app_option {
+                let option = match option.get_long() {
+                    Some(long) => format!("--{long}"),
+                    None => option.get_id().to_string(),
+                };
+                return Err(command.error(
+                    ErrorKind::ArgumentConflict,
+                    format!("'{option}' is an option of the app, it can't be given to '{name}'"),
+                ));
+            }
+        }
+        Self::from_arg_matches(&matches)
+    }
+}
+
+/// What the user asked rtkill to do
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Launch the interactive app (default)
+    Tui(TuiArgs),
+    /// Print the targets found, for scripts and reports
+    List(ListArgs),
+    /// Delete every target matching the filters, for cron jobs and CI runners
+    Clean(CleanArgs),
+    /// Prune artifacts in every target found, without any ui
+    Prune(PruneArgs),
+    /// Replace the identical artifacts of every target found by links
+    Dedupe(DedupeArgs),
+    /// Print the audit log
+    History(HistoryArgs),
+    /// Check the config file
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+/// What to do with the config file
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate the config file and report its unknown keys
+    Check {
+        /// the file to check, the one rtkill reads by default
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+    },
+}
+
+/// Folders searched for target dirs
+#[derive(Args, Debug, Clone, Default)]
+pub struct RootArgs {
+    /// folders to search, the current directory by default
+    #[arg(value_name = "ROOT", value_parser = existing_dir)]
+    roots: Vec<PathBuf>,
+}
+
+/// Criteria every listed target must match, the same ones as the selection prompt
+#[derive(Args, Debug, Clone, Default)]
+pub struct FilterArgs {
+    /// only the targets last built more than AGE ago, e.g: 60d, 8w or 12h
+    #[arg(long, visible_alias = "older-than", value_name = "AGE", value_parser = parse_age)]
+    older: Option<chrono::Duration>,
+    /// only the targets bigger than SIZE, e.g: 2G or 500M
+    #[arg(long, visible_alias = "larger-than", value_name = "SIZE", value_parser = check_size)]
+    larger: Option<String>,
+    /// only the targets inside DIR
+    #[arg(long, value_name = "DIR")]
+    under: Option<PathBuf>,
+    /// only the targets whose project name contains NAME
+    #[arg(long, value_name = "NAME")]
+    name: Option<String>,
+    /// only the targets whose project name or path matches GLOB, e.g: '*/work/**'
+    #[arg(long = "match", value_name = "GLOB", value_parser = parse_glob)]
+    matches: Option<Pattern>,
+    /// skip the targets whose project name or path matches GLOB, can be repeated
+    #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+    exclude: Vec<Pattern>,
+    /// only the targets whose project has no uncommitted git change
+    #[arg(long)]
+    no_git_changes: bool,
+}
+
+/// How the targets are deleted
+#[derive(Args, Debug, Clone, Default)]
+pub struct BackendArgs {
+    /// how the deleted targets are removed, `b` switches it in the app [default: remove]
+    #[arg(long, value_enum)]
+    backend: Option<BackendKind>,
+    /// hours quarantined targets are kept before their purge [default: 24]
+    #[arg(long, value_name = "HOURS")]
+    grace_hours: Option<u64>,
+    /// cargo clean only removes the release artifacts
+    #[arg(long)]
+    clean_release: bool,
+    /// cargo clean only removes the documentation
+    #[arg(long)]
+    clean_doc: bool,
+    /// cargo clean only removes the artifacts of this package
+    #[arg(long, value_name = "PKG")]
+    clean_package: Option<String>,
+    /// delete the targets protected by their Cargo.toml (`protect`, `max-age`), not the ones the config protects
+    #[arg(long)]
+    pub ignore_protection: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    /// deleted permanently
+    #[default]
+    Remove,
+    /// moved to the freedesktop trash
+    Trash,
+    /// renamed into a quarantine folder, purged once the grace period is over
+    Quarantine,
+    /// `cargo clean` ran from the project
+    CargoClean,
+}
+
+/// How the results are printed
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// aligned columns, for humans
+    #[default]
+    Table,
+    /// a json array
+    Json,
+    /// comma separated values, with a header
+    Csv,
+    /// one json object per line, each printed as soon as it's known
+    Ndjson,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub filters: FilterArgs,
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Space deletes the selected target right away, instead of marking it
+    #[arg(long)]
+    pub instant_delete: bool,
+    /// whether deleting the marked targets asks for a confirmation [default: always]
+    #[arg(long, value_enum)]
+    pub confirm: Option<ConfirmPolicy>,
+    /// how the last build date is shown, e.g: %Y-%m-%d [default: %d/%m/%Y]
+    #[arg(long, value_name = "FORMAT", value_parser = check_date_format)]
+    pub date_format: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub filters: FilterArgs,
+    /// how the targets are printed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct CleanArgs {
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub filters: FilterArgs,
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// only delete enough of the matching targets to free SIZE, e.g: 50G
+    #[arg(long, value_name = "SIZE", value_parser = check_size, conflicts_with = "until_free")]
+    free: Option<String>,
+    /// only delete enough of the matching targets for their filesystem to have PERCENT free, e.g: 20%
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    until_free: Option<f64>,
+    /// which targets are deleted first to meet `--free` or `--until-free`
+    #[arg(long, value_enum, default_value_t = BudgetPolicy::Oldest)]
+    pub policy: BudgetPolicy,
+    /// delete them, required unless it's a dry run
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct PruneArgs {
+    #[command(flatten)]
+    pub roots: RootArgs,
+    /// prune the artifacts that weren't built since DAYS days
+    #[arg(
+        long,
+        value_name = "DAYS",
+        conflicts_with = "toolchains",
+        value_parser = clap::value_parser!(u64).range(..=MAX_DAYS)
+    )]
+    pub days: Option<u64>,
+    /// prune the artifacts built by rustc versions that aren't installed anymore
+    #[arg(long)]
+    pub toolchains: bool,
+    /// prune them, without it only prints what would be pruned
+    #[arg(short, long)]
+    pub yes: bool,
+    /// prune the targets protected by their Cargo.toml (`protect`, `max-age`), not the ones the config protects
+    #[arg(long)]
+    pub ignore_protection: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DedupeArgs {
+    #[command(flatten)]
+    pub roots: RootArgs,
+    /// link with hardlinks, for filesystems without reflinks
+    #[arg(long)]
+    pub hardlinks: bool,
+    /// dedupe them, without it only prints what would be reclaimed
+    #[arg(short, long)]
+    pub yes: bool,
+    /// dedupe the targets protected by their Cargo.toml (`protect`, `max-age`), not the ones the config protects
+    #[arg(long)]
+    pub ignore_protection: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// only the records whose path contains PATH
+    #[arg(value_name = "PATH")]
+    pub path: Option<String>,
+    /// only the records of this project
+    #[arg(long, value_name = "NAME")]
+    pub project: Option<String>,
+    /// only the records of the last DAYS days
+    #[arg(
+        long,
+        value_name = "DAYS",
+        value_parser = clap::value_parser!(i64).range(0..=MAX_DAYS as i64)
+    )]
+    pub days: Option<i64>,
+    /// how the records are printed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+    /// same as `--format ndjson`
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+}
+
+/// Which artifacts the `prune` command removes
+pub enum PruneMode {
+    /// artifacts that weren't built since this number of days
+    Stale { max_age_days: u64 },
+    /// artifacts built by rustc versions that aren't installed anymore
+    OutdatedToolchains,
+}
+
+impl RootArgs {
+    /// absolute paths of the roots, the ones inside another root are dropped so that no target is found twice
+    ///
+    /// without any root, the ones of the config are searched, or the current directory
+    pub fn resolve(&self) -> Result<Vec<String>> {
+        let current_dir = env::current_dir()?;
+        let mut roots = match (self.roots.is_empty(), config::get().root_paths()) {
+            (false, _) => self.roots.clone(),
+            (true, roots) if !roots.is_empty() => roots,
+            (true, _) => vec![current_dir.clone()],
+        }
+        .into_iter()
+        .map(|root| current_dir.join(root).components().collect::<PathBuf>())
+        .collect::<Vec<_>>();
+        roots.sort();
+        roots.dedup_by(|root, parent| root.starts_with(parent));
+
+        roots
+            .into_iter()
+            .map(|root| {
+                root.to_str()
+                    .map(|root| root.to_string())
+                    .ok_or(anyhow!("'{}' isn't valid unicode", root.display()))
+            })
+            .collect()
+    }
+}
+
+impl FilterArgs {
+    pub fn predicates(&self) -> Result<Vec<Predicate>> {
+        let mut predicates = vec![];
+        if let Some(age) = self.older {
+            predicates.push(Predicate::OlderThan(age));
+        }
+        if let Some(size) = &self.larger {
+            predicates.push(Predicate::LargerThan(parse_bytes(size)?));
+        }
+        if let Some(dir) = &self.under {
+            predicates.push(Predicate::Under(env::current_dir()?.join(dir)));
+        }
+        if let Some(name) = &self.name {
+            predicates.push(Predicate::NameContains(name.clone()));
+        }
+        if let Some(glob) = &self.matches {
+            predicates.push(Predicate::Matches(glob.clone()));
+        }
+        predicates.extend(self.exclude.iter().cloned().map(Predicate::Excludes));
+        for glob in &config::get().exclude {
+            predicates.push(Predicate::Excludes(parse_glob(glob)?));
+        }
+        if self.no_git_changes {
+            predicates.push(Predicate::NoGitChanges);
+        }
+        Ok(predicates)
+    }
+}
+
+impl BackendArgs {
+    pub fn grace_period(&self) -> Duration {
+        let hours = self
+            .grace_hours
+            .unwrap_or(config::get().deletion.grace_hours);
+        Duration::from_secs(hours * 3600)
+    }
+
+    pub fn clean_options(&self) -> CleanOptions {
+        CleanOptions {
+            release: self.clean_release,
+            doc: self.clean_doc,
+            package: self.clean_package.clone(),
+        }
+    }
+
+    pub fn backend(&self) -> DeleteBackend {
+        match self.backend.unwrap_or(config::get().deletion.backend) {
+            BackendKind::Remove => DeleteBackend::Remove,
+            BackendKind::Trash => DeleteBackend::Trash,
+            BackendKind::Quarantine => DeleteBackend::Quarantine {
+                grace_period: self.grace_period(),
+            },
+            BackendKind::CargoClean => DeleteBackend::CargoClean(self.clean_options()),
+        }
+    }
+}
+
+impl CleanArgs {
+    pub fn budget(&self) -> Result<Option<Budget>> {
+        Ok(match (&self.free, self.until_free) {
+            (Some(size), _) => Some(Budget::Free(parse_bytes(size)?)),
+            (_, Some(percent)) => Some(Budget::UntilFree(percent)),
+            (None, None) => None,
+        })
+    }
+}
+
+impl PruneArgs {
+    pub fn mode(&self) -> PruneMode {
+        match self.toolchains {
+            true => PruneMode::OutdatedToolchains,
+            false => PruneMode::Stale {
+                max_age_days: self.days.unwrap_or(DEFAULT_MAX_AGE_DAYS),
+            },
+        }
+    }
+}
+
+impl HistoryArgs {
+    pub fn format(&self) -> OutputFormat {
+        match self.json {
+            true => OutputFormat::Ndjson,
+            false => self.format,
+        }
+    }
+}
+
+fn existing_dir(path: &str) -> Result<PathBuf> {
+    match Path::new(path).is_dir() {
+        true => Ok(PathBuf::from(path)),
+        false => Err(anyhow!("'{path}' isn't a directory")),
+    }
+}
+
+#[cfg(test)]
+mod args_tests {
+    use clap::CommandFactory;
+
+    use crate::{app::budget::Budget, backends::DeleteBackend};
+
+    use super::{Cli, Command, OutputFormat};
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let cli =
+            Cli::try_parse_args_from(["rtkill", "/tmp", "--backend", "trash", "--larger", "2G"])
+                .unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.tui.roots.roots.len(), 1);
+        assert_eq!(cli.tui.backend.backend(), DeleteBackend::Trash);
+        assert_eq!(cli.tui.filters.predicates().unwrap().len(), 1);
+
+        let cli =
+            Cli::try_parse_args_from(["rtkill", "--backend", "quarantine", "--grace-hours=2"])
+                .unwrap();
+        assert_eq!(
+            cli.tui.backend.backend(),
+            DeleteBackend::Quarantine {
+                grace_period: std::time::Duration::from_secs(7200)
+            }
+        );
+
+        let cli = Cli::try_parse_args_from(["rtkill", "history", "--json", "--dry-run"]).unwrap();
+        assert!(cli.dry_run);
+        assert!(
+            matches!(cli.command, Some(Command::History(h)) if h.format() == OutputFormat::Ndjson)
+        );
+
+        let cli = Cli::try_parse_args_from(["rtkill", "list", "/tmp", "--format", "csv"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::List(l)) if l.format == OutputFormat::Csv));
+
+        let cli = Cli::try_parse_args_from([
+            "rtkill",
+            "clean",
+            "--older-than",
+            "30d",
+            "--match",
+            "*/work/**",
+            "--exclude",
+            "keep-*",
+            "--exclude",
+            "*/vendor/*",
+            "--yes",
+        ])
+        .unwrap();
+        assert!(
+            matches!(cli.command, Some(Command::Clean(c)) if c.yes && c.filters.predicates().unwrap().len() == 4)
+        );
+
+        let cli =
+            Cli::try_parse_args_from(["rtkill", "clean", "--until-free", "20%", "-y"]).unwrap();
+        assert!(
+            matches!(cli.command, Some(Command::Clean(c)) if c.budget().unwrap() == Some(Budget::UntilFree(20.0)))
+        );
+        assert!(Cli::try_parse_args_from([
+            "rtkill",
+            "clean",
+            "--free",
+            "50G",
+            "--until-free",
+            "20"
+        ])
+        .is_err());
+
+        let cli =
+            Cli::try_parse_args_from(["rtkill", "--config", "/tmp/rtkill.toml", "list"]).unwrap();
+        assert!(cli.config.is_some() && matches!(cli.command, Some(Command::List(_))));
+        assert!(Cli::try_parse_args_from(["rtkill", "--instant-delete", "list"]).is_err());
+        assert!(Cli::try_parse_args_from(["rtkill", "config", "check", "--dry-run"]).is_ok());
+
+        assert!(Cli::try_parse_args_from(["rtkill", "/does/not/exist"]).is_err());
+        assert!(Cli::try_parse_args_from(["rtkill", "clean", "--exclude", "["]).is_err());
+        assert!(Cli::try_parse_args_from(["rtkill", "--larger", "2 parsecs"]).is_err());
+        assert!(
+            Cli::try_parse_args_from(["rtkill", "prune", "--days", "3", "--toolchains"]).is_err()
+        );
+        assert!(Cli::try_parse_args_from(["rtkill", "--trash"]).is_err());
+        assert!(
+            Cli::try_parse_args_from(["rtkill", "prune", "--days", "999999999999999"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_args_from(["rtkill", "history", "--days", "999999999999999"]).is_err()
+        );
+    }
+}
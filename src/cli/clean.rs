@@ -0,0 +1,285 @@
+use std::{path::Path, process::ExitCode};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::{
+    app::{
+        budget::{check_backend, plan_budget, BudgetProgress},
+        predicate::matches_all,
+        scan_target_dirs, TargetDir,
+    },
+    artifacts::locks::is_locked,
+    audit::{self, AuditAction, AuditOutcome, AuditRecord},
+    backends::{safety::verify_target, DeleteBackend, PartialRemoval, RemovalProgress},
+    utils::{bytes_len_to_string_prefix, disk::DiskSpace, dry_run},
+};
+
+use super::args::CleanArgs;
+
+/// How `clean` went, its exit code tells it to the cron wrappers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanStatus {
+    /// every matching target was deleted, or would have been in a dry run, or enough of them to meet the budget
+    Cleaned,
+    /// no target matched
+    NothingToDo,
+    /// some targets, or a part of them, couldn't be deleted, some were locked by a running cargo build, or the budget wasn't met
+    PartialFailure,
+}
+
+impl CleanStatus {
+    /// 1 is left to the errors that stopped rtkill, and 2 to the invalid arguments
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            CleanStatus::Cleaned => 0,
+            CleanStatus::PartialFailure => 3,
+            CleanStatus::NothingToDo => 4,
+        })
+    }
+}
+
+/// A target `clean` deleted, or tried to
+#[derive(Serialize)]
+struct CleanedTarget {
+    project: String,
+    path: String,
+    size_bytes: u64,
+    /// nothing for the trash and the quarantine, they only move the target
+    bytes_freed: u64,
+    /// "done", "partial" or "failed"
+    outcome: AuditOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Where `--free` or `--until-free` got
+#[derive(Serialize)]
+struct BudgetSummary {
+    /// e.g: "free 50.0 GiB"
+    goal: String,
+    policy: &'static str,
+    reached: bool,
+    /// e.g: "12.0 GiB of 50.0 GiB freed"
+    progress: String,
+    /// planned targets left on the disk since the budget was met before their turn
+    skipped: usize,
+}
+
+/// Printed on stdout once `clean` is done, the progress goes to stderr
+#[derive(Serialize)]
+struct CleanSummary {
+    status: CleanStatus,
+    dry_run: bool,
+    backend: &'static str,
+    /// targets found under the roots, matching or not
+    found: usize,
+    /// matching targets left since they're pinned, or their Cargo.toml or the config protects them
+    protected: usize,
+    /// matching targets left since a running cargo build locks them
+    deferred: usize,
+    cleaned: usize,
+    failed: usize,
+    /// total size of the matching targets
+    size_bytes: u64,
+    bytes_freed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budget: Option<BudgetSummary>,
+    targets: Vec<CleanedTarget>,
+}
+
+/// delete every target matching the filters, or only enough of them to meet the budget, `--yes` is required unless it's a dry run
+pub fn clean(args: CleanArgs) -> Result<ExitCode> {
+    let roots = args.roots.resolve()?;
+    let filters = args.filters.predicates()?;
+    let backend = args.backend.backend();
+    let budget = args.budget()?;
+    if budget.is_some() {
+        check_backend(&backend)?;
+    }
+
+    let (mut found, mut matching, mut protected) = (0, vec![], 0);
+    scan_target_dirs(&roots, |mut target| {
+        found += 1;
+        if !matches_all(&filters, &mut target) {
+            return;
+        }
+        match target.is_protected(args.backend.ignore_protection) {
+            true => {
+                let reason = target.protection_reason().unwrap_or_default();
+                eprintln!("protected\t{}\t{reason}", target.path);
+                protected += 1;
+            }
+            false => matching.push(target),
+        }
+    });
+
+    // the planned targets, in the order they're deleted
+    let mut plan = None;
+    if let Some(budget) = budget {
+        let planned = plan_budget(budget, args.policy, &matching, |path| {
+            DiskSpace::of(path).ok()
+        });
+        matching = planned
+            .paths
+            .iter()
+            .filter_map(|path| matching.iter().find(|t| &t.path == path).cloned())
+            .collect();
+        plan = Some((
+            BudgetProgress::new(budget, planned.paths),
+            planned.reachable,
+        ));
+    }
+
+    let size = matching.iter().map(|t| t.size_bytes).sum::<u64>();
+    if !matching.is_empty() && !args.yes && !dry_run::is_enabled() {
+        return Err(anyhow!(
+            "{} targets ({}) would be {}, run again with --yes to delete them or with --dry-run to only list them",
+            matching.len(),
+            bytes_len_to_string_prefix(size),
+            backend.outcome()
+        ));
+    }
+
+    let (mut targets, mut skipped, mut deferred) = (vec![], 0, 0);
+    for target in &matching {
+        // statvfs tells whether the deletions so far already met the budget
+        if let Some((progress, _)) = &plan {
+            if !dry_run::is_enabled() && !progress.is_needed(&target.path) {
+                skipped += 1;
+                continue;
+            }
+        }
+        // deleting it would break the build, the next run deletes it
+        if is_locked(Path::new(&target.path)) {
+            eprintln!("deferred\t{}\tlocked by a running cargo build", target.path);
+            deferred += 1;
+            continue;
+        }
+        let scan_root = roots
+            .iter()
+            .find(|root| Path::new(&target.path).starts_with(root))
+            .cloned()
+            .unwrap_or_default();
+        let cleaned = clean_target(
+            target,
+            Path::new(&scan_root),
+            &backend,
+            args.backend.ignore_protection,
+        );
+        eprintln!(
+            "{}\t{}\t{}{}",
+            match dry_run::is_enabled() {
+                true => "would delete",
+                false => cleaned.outcome.name(),
+            },
+            cleaned.path,
+            bytes_len_to_string_prefix(cleaned.bytes_freed),
+            cleaned
+                .error
+                .as_ref()
+                .map(|error| format!("\t{error}"))
+                .unwrap_or_default()
+        );
+        targets.push(cleaned);
+    }
+
+    let failed = targets
+        .iter()
+        .filter(|t| t.outcome != AuditOutcome::Done)
+        .count();
+    let budget = plan.map(|(progress, reachable)| BudgetSummary {
+        goal: progress.budget.describe(),
+        policy: args.policy.describe(),
+        // nothing is freed in a dry run, only the plan tells
+        reached: match dry_run::is_enabled() {
+            true => reachable,
+            false => progress.is_reached(),
+        },
+        progress: progress.status(),
+        skipped,
+    });
+    let reached = budget.as_ref().map(|b| b.reached).unwrap_or(true);
+    let status = match (targets.is_empty(), failed + deferred, reached) {
+        (true, 0, true) => CleanStatus::NothingToDo,
+        (false, 0, true) => CleanStatus::Cleaned,
+        _ => CleanStatus::PartialFailure,
+    };
+    let summary = CleanSummary {
+        status,
+        dry_run: dry_run::is_enabled(),
+        backend: backend.name(),
+        found,
+        protected,
+        deferred,
+        cleaned: targets.len() - failed,
+        failed,
+        size_bytes: size,
+        bytes_freed: targets.iter().map(|t| t.bytes_freed).sum(),
+        budget,
+        targets,
+    };
+    println!("{}", serde_json::to_string(&summary)?);
+    Ok(status.exit_code())
+}
+
+/// check the target is still one and delete it, the error is kept in the result rather than stopping the others
+fn clean_target(
+    target: &TargetDir,
+    scan_root: &Path,
+    backend: &DeleteBackend,
+    ignore_protection: bool,
+) -> CleanedTarget {
+    let path = Path::new(&target.path);
+    let mut removed = 0;
+    let result = verify_target(path, scan_root, false, ignore_protection).and_then(|metadata| {
+        backend.remove(path, &metadata.keep, |removal| {
+            if let RemovalProgress::Removed { bytes, .. } = removal {
+                removed = bytes
+            }
+        })
+    });
+    // `cargo clean --release` (or `--doc`, `-p`) and the folders kept by the Cargo.toml leave a part of the target
+    let remaining = match path.exists() && !dry_run::is_enabled() {
+        true => fs_extra::dir::get_size(path).unwrap_or(0),
+        false => 0,
+    };
+    let freed = match backend {
+        _ if dry_run::is_enabled() => target.size_bytes,
+        DeleteBackend::Remove => removed,
+        DeleteBackend::CargoClean(_) => target.size_bytes.saturating_sub(remaining),
+        DeleteBackend::Trash | DeleteBackend::Quarantine { .. } => 0,
+    };
+
+    let record = AuditRecord::new(
+        AuditAction::Delete,
+        &target.path,
+        &target.project_name,
+        backend.name(),
+    )
+    .freed(freed);
+    let record = match &result {
+        Ok(()) => record,
+        Err(e) => {
+            let error = match e.downcast_ref::<PartialRemoval>() {
+                Some(partial) => format!("{e}: {}", partial.failures.join(", ")),
+                None => e.to_string(),
+            };
+            record.failed(error, removed > 0)
+        }
+    };
+    // a failure to write the log doesn't undo the deletion
+    if let Err(e) = audit::log(&record) {
+        eprintln!("Couldn't write the audit log: {e}");
+    }
+
+    CleanedTarget {
+        project: target.project_name.clone(),
+        path: target.path.clone(),
+        size_bytes: target.size_bytes,
+        bytes_freed: freed,
+        outcome: record.outcome,
+        error: record.error,
+    }
+}
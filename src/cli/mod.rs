@@ -0,0 +1,393 @@
+pub mod args;
+mod clean;
+mod output;
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::{
+    app::{
+        collect_target_dirs,
+        predicate::{matches_all, Predicate},
+        scan_target_dirs, TargetDir,
+    },
+    artifacts::{
+        dedupe::{DedupePlan, LinkKind},
+        prune::PrunePlan,
+        toolchains::INSTALLED_RUSTC,
+    },
+    audit::{self, AuditAction, AuditRecord, HistoryQuery},
+    backends::safety::verify_target,
+    config::{self, Config},
+    utils::{bytes_len_to_string_prefix, dry_run},
+};
+
+use self::{
+    args::{Command, ConfigCommand, OutputFormat, PruneMode},
+    output::{Printer, Row},
+};
+
+/// A target as `list` prints it
+#[derive(Serialize)]
+struct ListedTarget {
+    project: String,
+    path: String,
+    manifest_path: String,
+    /// "package" or "workspace"
+    kind: &'static str,
+    size_bytes: u64,
+    /// e.g: "1.2 GiB"
+    size: String,
+    /// RFC 3339, when the target was last written, i.e: last built
+    last_modified: String,
+    /// why it can't be deleted, e.g: "pinned" or "its Cargo.toml protects it"
+    protected: Option<String>,
+}
+
+impl From<&TargetDir> for ListedTarget {
+    fn from(target: &TargetDir) -> Self {
+        Self {
+            project: target.project_name.clone(),
+            path: target.path.clone(),
+            manifest_path: target.manifest_path.clone(),
+            kind: target.kind.name(),
+            size_bytes: target.size_bytes,
+            size: bytes_len_to_string_prefix(target.size_bytes),
+            last_modified: target.last_modified.to_rfc3339(),
+            protected: target.protection_reason(),
+        }
+    }
+}
+
+impl Row for ListedTarget {
+    const COLUMNS: &'static [&'static str] = &[
+        "project",
+        "path",
+        "manifest_path",
+        "kind",
+        "size_bytes",
+        "size",
+        "last_modified",
+        "protected",
+    ];
+    const TABLE_COLUMNS: &'static [usize] = &[0, 3, 5, 6, 1, 7];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.project.clone(),
+            self.path.clone(),
+            self.manifest_path.clone(),
+            self.kind.to_string(),
+            self.size_bytes.to_string(),
+            self.size.clone(),
+            self.last_modified.clone(),
+            self.protected.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Row for AuditRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "timestamp",
+        "user",
+        "action",
+        "backend",
+        "bytes_freed",
+        "freed",
+        "outcome",
+        "project",
+        "path",
+        "error",
+    ];
+    const TABLE_COLUMNS: &'static [usize] = &[0, 1, 2, 3, 5, 6, 8, 9];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.timestamp.clone(),
+            self.user.clone(),
+            self.action.name().to_string(),
+            self.backend.clone(),
+            self.bytes_freed.to_string(),
+            bytes_len_to_string_prefix(self.bytes_freed),
+            self.outcome.name().to_string(),
+            self.project.clone(),
+            self.path.clone(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Command {
+    /// run a non-interactive command, printing its result to stdout, only `clean` has its own exit codes
+    pub fn run(self) -> Result<ExitCode> {
+        match self {
+            Command::Tui(_) => Ok(()),
+            Command::Clean(args) => return clean::clean(args),
+            Command::Config(ConfigCommand::Check { path }) => return check_config(path),
+            Command::List(args) => list(
+                args.roots.resolve()?,
+                args.filters.predicates()?,
+                args.format,
+            ),
+            Command::Prune(args) => prune(
+                args.roots.resolve()?,
+                args.mode(),
+                args.yes,
+                args.ignore_protection,
+            ),
+            Command::Dedupe(args) => dedupe(
+                args.roots.resolve()?,
+                match args.hardlinks {
+                    true => LinkKind::Hardlink,
+                    false => LinkKind::Reflink,
+                },
+                args.yes,
+                args.ignore_protection,
+            ),
+            Command::History(args) => history(
+                HistoryQuery {
+                    path: args.path.clone(),
+                    project: args.project.clone(),
+                    since: args
+                        .days
+                        .map(|days| Local::now() - chrono::Duration::days(days)),
+                },
+                args.format(),
+            ),
+        }
+        .map(|_| ExitCode::SUCCESS)
+    }
+}
+
+/// the targets found, the protected ones are left out and listed on stderr
+fn unprotected_target_dirs(roots: &[String], ignore_protection: bool) -> Vec<TargetDir> {
+    let mut targets = collect_target_dirs(roots);
+    targets.retain(|target| {
+        if !target.is_protected(ignore_protection) {
+            return true;
+        }
+        let reason = target.protection_reason().unwrap_or_default();
+        eprintln!("protected\t{}\t{reason}", target.path);
+        false
+    });
+    targets
+}
+
+fn prune(roots: Vec<String>, mode: PruneMode, yes: bool, ignore_protection: bool) -> Result<()> {
+    let (mut total, mut failed) = (0, 0);
+    for target in unprotected_target_dirs(&roots, ignore_protection) {
+        let path = Path::new(&target.path);
+        // the folders its Cargo.toml keeps are never pruned
+        let scan_root = roots
+            .iter()
+            .find(|root| path.starts_with(root))
+            .cloned()
+            .unwrap_or_default();
+        let keep = match verify_target(path, Path::new(&scan_root), false, ignore_protection) {
+            Ok(metadata) => metadata.keep,
+            Err(e) => {
+                eprintln!("failed\t{}\t{e}", target.path);
+                failed += 1;
+                continue;
+            }
+        };
+        let plan = match mode {
+            PruneMode::Stale { max_age_days } => {
+                PrunePlan::stale_units(path, Duration::from_secs(max_age_days * 24 * 3600))
+            }
+            PruneMode::OutdatedToolchains => {
+                PrunePlan::outdated_toolchain_units(path, &INSTALLED_RUSTC)
+            }
+        }
+        .without_kept(&keep);
+        if plan.is_empty() {
+            continue;
+        }
+
+        let size = match yes {
+            true => {
+                let record = AuditRecord::new(
+                    AuditAction::Prune,
+                    &target.path,
+                    &target.project_name,
+                    "remove",
+                );
+                let outcome = plan.execute();
+                audit::log(&match outcome.failures.is_empty() {
+                    true => record.freed(outcome.freed),
+                    false => record.freed(outcome.freed).failed(
+                        format!(
+                            "{} files couldn't be removed: {}",
+                            outcome.failures.len(),
+                            outcome.failures.join(", ")
+                        ),
+                        outcome.freed > 0,
+                    ),
+                })?;
+                for failure in &outcome.failures {
+                    eprintln!("failed\t{failure}");
+                }
+                failed += usize::from(!outcome.failures.is_empty());
+                outcome.freed
+            }
+            false => plan.size(),
+        };
+        total += size;
+        println!(
+            "{}\t{}\t{} units\t{}",
+            target.project_name,
+            target.path,
+            plan.units.len(),
+            bytes_len_to_string_prefix(size)
+        );
+    }
+
+    let reason = match mode {
+        PruneMode::Stale { max_age_days } => {
+            format!("artifacts unused for more than {max_age_days} days")
+        }
+        PruneMode::OutdatedToolchains => "built by rustc versions you no longer have".to_string(),
+    };
+    match yes {
+        true if dry_run::is_enabled() => {
+            println!(
+                "Would prune {} ({reason})",
+                bytes_len_to_string_prefix(total)
+            )
+        }
+        true => println!("Pruned {} ({reason})", bytes_len_to_string_prefix(total)),
+        false => println!(
+            "{} can be pruned ({reason}), run again with --yes to prune them",
+            bytes_len_to_string_prefix(total)
+        ),
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} targets couldn't be fully pruned"));
+    }
+    Ok(())
+}
+
+fn dedupe(roots: Vec<String>, link: LinkKind, yes: bool, ignore_protection: bool) -> Result<()> {
+    let targets = unprotected_target_dirs(&roots, ignore_protection)
+        .into_iter()
+        .map(|t| PathBuf::from(t.path))
+        .collect::<Vec<_>>();
+    let plan = DedupePlan::new(&targets);
+
+    let link_name = match link {
+        LinkKind::Reflink => "reflinks",
+        LinkKind::Hardlink => "hardlinks",
+    };
+    if !yes {
+        for group in &plan.groups {
+            println!(
+                "{}\t{} copies\t{}",
+                group.paths[0].display(),
+                group.paths.len(),
+                bytes_len_to_string_prefix(group.reclaimable())
+            );
+        }
+        println!(
+            "{} files of {} targets are duplicates, replacing them by {link_name} would free {}, run again with --yes to dedupe them",
+            plan.duplicates(),
+            targets.len(),
+            bytes_len_to_string_prefix(plan.reclaimable())
+        );
+        return Ok(());
+    }
+
+    let backend = match link {
+        LinkKind::Reflink => "reflink",
+        LinkKind::Hardlink => "hardlink",
+    };
+    let record = AuditRecord::new(AuditAction::Dedupe, &roots.join(" "), "", backend);
+    let outcome = plan.execute(link);
+    audit::log(&match &outcome {
+        Ok(outcome) if outcome.failures > 0 => record.freed(outcome.freed).failed(
+            format!("{} files couldn't be linked", outcome.failures),
+            true,
+        ),
+        Ok(outcome) => record.freed(outcome.freed),
+        Err(e) => record.failed(e.to_string(), false),
+    })?;
+    let outcome = outcome?;
+    println!(
+        "{} {} files by {link_name}, {} freed",
+        match dry_run::is_enabled() {
+            true => "Would replace",
+            false => "Replaced",
+        },
+        outcome.linked_files,
+        bytes_len_to_string_prefix(outcome.freed)
+    );
+    if outcome.failures > 0 {
+        return Err(anyhow!("{} files couldn't be linked", outcome.failures));
+    }
+    Ok(())
+}
+
+/// print the targets as they're found, the table and the json array once they all are
+fn list(roots: Vec<String>, filters: Vec<Predicate>, format: OutputFormat) -> Result<()> {
+    let mut printer = Printer::new(format);
+    let mut result = Ok(());
+    scan_target_dirs(&roots, |mut target| {
+        if result.is_ok() && matches_all(&filters, &mut target) {
+            result = printer.push(ListedTarget::from(&target));
+        }
+    });
+    result?;
+    printer.finish()
+}
+
+fn history(query: HistoryQuery, format: OutputFormat) -> Result<()> {
+    let records = audit::history(&query)?;
+    if records.is_empty() && format == OutputFormat::Table {
+        println!(
+            "Nothing in the audit log ({}) matches",
+            audit::audit_log_path()?.display()
+        );
+        return Ok(());
+    }
+
+    let mut printer = Printer::new(format);
+    for record in records {
+        printer.push(record)?;
+    }
+    printer.finish()
+}
+
+/// validate the config file, the default one unless `path` is given, an unknown key fails the check too so that a typo doesn't go unnoticed
+fn check_config(path: Option<PathBuf>) -> Result<ExitCode> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let path = config::default_path()?;
+            if !path.exists() {
+                println!(
+                    "No config file at {}, the defaults are used",
+                    path.display()
+                );
+                return Ok(ExitCode::SUCCESS);
+            }
+            path
+        }
+    };
+
+    let (_, unknown_keys) = Config::read(&path)?;
+    if unknown_keys.is_empty() {
+        println!("{} is valid", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+    for key in &unknown_keys {
+        eprintln!("{}: unknown key '{key}'", path.display());
+    }
+    Ok(ExitCode::FAILURE)
+}
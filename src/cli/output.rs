@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::args::OutputFormat;
+
+/// Something a command prints, one per line or per object
+pub trait Row: Serialize {
+    /// header of the csv output, one per cell
+    const COLUMNS: &'static [&'static str];
+    /// indexes of the columns shown in the table output, the ones a human reads
+    const TABLE_COLUMNS: &'static [usize];
+
+    fn cells(&self) -> Vec<String>;
+}
+
+/// Prints rows in the chosen format, csv and ndjson rows are printed as soon as they're pushed
+///
+/// the table and the json array need every row first, they're printed by `finish`
+pub struct Printer<T: Row> {
+    format: OutputFormat,
+    buffered: Vec<T>,
+    header_printed: bool,
+}
+
+impl<T: Row> Printer<T> {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            buffered: vec![],
+            header_printed: false,
+        }
+    }
+
+    pub fn push(&mut self, row: T) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        match self.format {
+            OutputFormat::Ndjson => writeln!(stdout, "{}", serde_json::to_string(&row)?)?,
+            OutputFormat::Csv => {
+                if !self.header_printed {
+                    writeln!(stdout, "{}", T::COLUMNS.join(","))?;
+                    self.header_printed = true;
+                }
+                let cells = row
+                    .cells()
+                    .iter()
+                    .map(|c| csv_escape(c))
+                    .collect::<Vec<_>>();
+                writeln!(stdout, "{}", cells.join(","))?;
+            }
+            OutputFormat::Table | OutputFormat::Json => self.buffered.push(row),
+        }
+        // a script reading the stream gets each row right away
+        stdout.flush()?;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        match self.format {
+            OutputFormat::Json => {
+                writeln!(stdout, "{}", serde_json::to_string_pretty(&self.buffered)?)?
+            }
+            OutputFormat::Table => {
+                for line in table(&self.buffered) {
+                    writeln!(stdout, "{line}")?;
+                }
+            }
+            OutputFormat::Csv | OutputFormat::Ndjson => (),
+        }
+        Ok(())
+    }
+}
+
+/// the table columns of the rows, aligned, under a header
+fn table<T: Row>(rows: &[T]) -> Vec<String> {
+    let header = T::TABLE_COLUMNS
+        .iter()
+        .map(|&i| T::COLUMNS[i].to_uppercase())
+        .collect::<Vec<_>>();
+    let lines = std::iter::once(header)
+        .chain(rows.iter().map(|row| {
+            let cells = row.cells();
+            T::TABLE_COLUMNS.iter().map(|&i| cells[i].clone()).collect()
+        }))
+        .collect::<Vec<_>>();
+
+    let widths = (0..T::TABLE_COLUMNS.len())
+        .map(|i| {
+            lines
+                .iter()
+                .map(|l| l[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    lines
+        .iter()
+        .map(|cells| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// quote the cell if it has a comma, a quote or a line break, quotes are doubled
+fn csv_escape(cell: &str) -> String {
+    match cell.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod output_tests {
+    use serde::Serialize;
+
+    use super::{csv_escape, table, Row};
+
+    #[derive(Serialize)]
+    struct Fruit {
+        name: String,
+        count: u64,
+    }
+
+    impl Row for Fruit {
+        const COLUMNS: &'static [&'static str] = &["name", "count"];
+        const TABLE_COLUMNS: &'static [usize] = &[1, 0];
+
+        fn cells(&self) -> Vec<String> {
+            vec![self.name.clone(), self.count.to_string()]
+        }
+    }
+
+    #[test]
+    fn test_table() {
+        let fruits = [
+            Fruit {
+                name: "apple".to_string(),
+                count: 12,
+            },
+            Fruit {
+                name: "fig".to_string(),
+                count: 3,
+            },
+        ];
+        assert_eq!(
+            table(&fruits),
+            ["COUNT  NAME", "12     apple", "3      fig"]
+        );
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use crossterm::event::KeyCode;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// What a key does in the target list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Up,
+    Down,
+    /// mark the highlighted target, or delete it with `--instant-delete`
+    Mark,
+    MarkAll,
+    InvertMarks,
+    /// pin the highlighted project, or unpin it
+    Pin,
+    MarkByCriteria,
+    FreeSpace,
+    DeleteMarked,
+    DeletionErrors,
+    Backend,
+    Restore,
+    History,
+    Undo,
+    Quit,
+    Refresh,
+    Explore,
+    Open,
+    Prune,
+    PruneToolchains,
+    Analysis,
+    Dedupe,
+}
+
+/// Keys of each action of the target list, the ones missing from the config keep their default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Keymap(BTreeMap<Action, Keys>);
+
+/// One key, e.g: "x", "X", "space", "enter", "delete" or "f2", or a list of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keys(pub Vec<KeyCode>);
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        use KeyCode::{Char, Delete, Down as DownKey, Enter, Up as UpKey};
+        Self(BTreeMap::from([
+            (Up, Keys(vec![UpKey])),
+            (Down, Keys(vec![DownKey])),
+            (Mark, Keys(vec![Char(' ')])),
+            (MarkAll, Keys(vec![Char('s')])),
+            (InvertMarks, Keys(vec![Char('i')])),
+            (Pin, Keys(vec![Char('P')])),
+            (MarkByCriteria, Keys(vec![Char('m')])),
+            (FreeSpace, Keys(vec![Char('f')])),
+            (DeleteMarked, Keys(vec![Char('x'), Delete])),
+            (DeletionErrors, Keys(vec![Char('e')])),
+            (Backend, Keys(vec![Char('b')])),
+            (Restore, Keys(vec![Char('u')])),
+            (History, Keys(vec![Char('h')])),
+            (Undo, Keys(vec![Char('z')])),
+            (Quit, Keys(vec![Char('q')])),
+            (Refresh, Keys(vec![Char('r')])),
+            (Explore, Keys(vec![Enter])),
+            (Open, Keys(vec![Char('o')])),
+            (Prune, Keys(vec![Char('p')])),
+            (PruneToolchains, Keys(vec![Char('t')])),
+            (Analysis, Keys(vec![Char('a')])),
+            (Dedupe, Keys(vec![Char('d')])),
+        ]))
+    }
+}
+
+impl Keymap {
+    /// the action bound to this key, if any
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(_, keys)| keys.0.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// e.g: "x" for `DeleteMarked`, shown in the controls
+    pub fn key_name(&self, action: Action) -> String {
+        self.0
+            .get(&action)
+            .and_then(|keys| keys.0.first())
+            .map(|key| key_name(*key))
+            .unwrap_or_default()
+    }
+
+    /// a key bound to two actions would only ever run the first one
+    pub fn check(&self) -> Result<()> {
+        let mut bound = BTreeMap::new();
+        for (action, keys) in &self.0 {
+            for key in &keys.0 {
+                if let Some(other) = bound.insert(key_name(*key), action) {
+                    return Err(anyhow!(
+                        "'{}' is bound to both {} and {}",
+                        key_name(*key),
+                        action_name(*other),
+                        action_name(*action)
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn action_name(action: Action) -> String {
+    serde_json::to_value(action)
+        .ok()
+        .and_then(|name| name.as_str().map(|name| name.to_string()))
+        .unwrap_or_default()
+}
+
+/// the keys named in the config, a single character is taken as is so that "X" is shift+x
+pub fn parse_key(key: &str) -> Result<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    Ok(match key.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "esc" => KeyCode::Esc,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        name => match name.strip_prefix('f').map(|n| n.parse::<u8>()) {
+            Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+            _ => return Err(anyhow!("unknown key '{key}', e.g: x, space, enter or f2")),
+        },
+    })
+}
+
+/// inverse of [parse_key], capitalized the way the controls show them
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        key => format!("{key:?}"),
+    }
+}
+
+impl Serialize for Keys {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [key] => serializer.serialize_str(&key_name(*key)),
+            keys => serializer.collect_seq(keys.iter().map(|key| key_name(*key))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+        let names = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(name) => vec![name],
+            OneOrMany::Many(names) => names,
+        };
+        names
+            .iter()
+            .map(|name| parse_key(name))
+            .collect::<Result<Vec<_>>>()
+            .map(Keys)
+            .map_err(D::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut keymap = Keymap::default();
+        keymap
+            .0
+            .extend(BTreeMap::<Action, Keys>::deserialize(deserializer)?);
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod keymap_tests {
+    use crossterm::event::KeyCode;
+
+    use super::{parse_key, Action, Keymap};
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("X").unwrap(), KeyCode::Char('X'));
+        assert_eq!(parse_key("Space").unwrap(), KeyCode::Char(' '));
+        assert_eq!(parse_key("f2").unwrap(), KeyCode::F(2));
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("hyper").is_err());
+    }
+
+    #[test]
+    fn test_keymap() {
+        let keymap: Keymap =
+            toml::from_str("quit = \"Q\"\ndelete-marked = [\"D\", \"delete\"]").unwrap();
+        assert_eq!(keymap.action(KeyCode::Char('Q')), Some(Action::Quit));
+        assert_eq!(keymap.action(KeyCode::Char('q')), None);
+        assert_eq!(keymap.action(KeyCode::Delete), Some(Action::DeleteMarked));
+        assert_eq!(
+            keymap.action(KeyCode::Char('m')),
+            Some(Action::MarkByCriteria)
+        );
+        assert_eq!(keymap.key_name(Action::Mark), "Space");
+        assert!(keymap.check().is_ok());
+
+        let keymap: Keymap = toml::from_str("quit = \"m\"").unwrap();
+        assert!(keymap.check().is_err());
+    }
+}
@@ -0,0 +1,316 @@
+pub mod keymap;
+pub mod project;
+pub mod theme;
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::predicate::{expand_path, parse_glob},
+    backends::quarantine::DEFAULT_GRACE_PERIOD,
+    cli::args::BackendKind,
+    utils::config_dir,
+};
+
+use self::{keymap::Keymap, theme::Theme};
+
+/// file of the config dir read at startup
+const CONFIG_FILE: &str = "config.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// What the user configured in `$XDG_CONFIG_HOME/rtkill/config.toml`, the command line options override it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// folders searched when none is given on the command line, relative to the home folder
+    pub roots: Vec<String>,
+    /// globs of the project names or paths never listed, added to `--exclude`
+    pub exclude: Vec<String>,
+    /// folders whose targets are never deleted
+    pub protected: Vec<String>,
+    pub deletion: DeletionConfig,
+    pub ui: UiConfig,
+    pub theme: Theme,
+    pub keymap: Keymap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DeletionConfig {
+    /// used unless `--backend` is given
+    pub backend: BackendKind,
+    /// hours quarantined targets are kept before their purge
+    pub grace_hours: u64,
+    pub confirm: ConfirmPolicy,
+    /// Space deletes the highlighted target right away, like `--instant-delete`
+    pub instant_delete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct UiConfig {
+    /// how the last build date of the targets is shown, see chrono's strftime
+    pub date_format: String,
+    pub size_units: SizeUnits,
+    /// milliseconds between two frames while the user is active
+    pub refresh_ms: u64,
+    /// milliseconds between two frames once the user has been idle for a while
+    pub idle_refresh_ms: u64,
+}
+
+/// Whether deleting the marked targets asks for a confirmation first
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfirmPolicy {
+    /// a popup lists them first
+    #[default]
+    Always,
+    /// they're queued right away
+    Never,
+}
+
+/// How the sizes are printed
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeUnits {
+    /// KiB, MiB, GiB: powers of 1024
+    #[default]
+    Binary,
+    /// kB, MB, GB: powers of 1000
+    Decimal,
+}
+
+impl Default for DeletionConfig {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::default(),
+            grace_hours: DEFAULT_GRACE_PERIOD.as_secs() / 3600,
+            confirm: ConfirmPolicy::default(),
+            instant_delete: false,
+        }
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            date_format: "%d/%m/%Y".to_string(),
+            size_units: SizeUnits::default(),
+            refresh_ms: 100,
+            idle_refresh_ms: 1000,
+        }
+    }
+}
+
+/// the config of this session, the defaults until [init] is called
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// the colors of this session
+pub fn theme() -> &'static Theme {
+    &get().theme
+}
+
+/// set once at startup, for the whole session
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// `$XDG_CONFIG_HOME/rtkill/config.toml`
+pub fn default_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(CONFIG_FILE))
+}
+
+impl Config {
+    /// read the config file, the default one may not exist but `path` must, the unknown keys are ignored
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (config, _) = match path {
+            Some(path) => Self::read(path)?,
+            None => match Self::read(&default_path()?) {
+                Err(e) if is_not_found(&e) => return Ok(Self::default()),
+                result => result?,
+            },
+        };
+        Ok(config)
+    }
+
+    /// the config of the file and its unknown keys, e.g: "ui.colour"
+    pub fn read(path: &Path) -> Result<(Self, Vec<String>)> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<(Self, Vec<String>)> {
+        let mut value = toml::from_str::<toml::Value>(text)?;
+        let known = toml::Value::try_from(Config::default())?;
+        let mut unknown_keys = vec![];
+        remove_unknown_keys(&mut value, &known, "", &mut unknown_keys);
+
+        let config = value.try_into::<Config>()?;
+        config.check()?;
+        Ok((config, unknown_keys))
+    }
+
+    /// what serde can't tell, e.g: a glob that doesn't compile
+    fn check(&self) -> Result<()> {
+        for glob in &self.exclude {
+            parse_glob(glob).context("exclude")?;
+        }
+        for root in &self.roots {
+            if !home_path(root).is_dir() {
+                return Err(anyhow!("roots: '{root}' isn't a directory"));
+            }
+        }
+        check_date_format(&self.ui.date_format).context("ui.date-format")?;
+        if self.ui.refresh_ms == 0 || self.ui.idle_refresh_ms == 0 {
+            return Err(anyhow!("ui: the refresh rates must be at least 1ms"));
+        }
+        self.keymap.check().context("keymap")
+    }
+
+    /// the folders of `roots`, with `~` expanded
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        self.roots.iter().map(|root| home_path(root)).collect()
+    }
+
+    /// whether the target is inside one of the `protected` folders
+    pub fn is_protected(&self, target: &Path) -> bool {
+        self.protected.iter().any(|protected| {
+            let protected = home_path(protected);
+            let protected = protected.canonicalize().unwrap_or(protected);
+            target.starts_with(protected)
+        })
+    }
+}
+
+impl UiConfig {
+    /// the frame rate, slower once the user has been idle for a while
+    pub fn refresh_rate(&self, idle: bool) -> Duration {
+        Duration::from_millis(match idle {
+            true => self.idle_refresh_ms,
+            false => self.refresh_ms,
+        })
+    }
+}
+
+/// chrono panics when an invalid format is printed, it's rejected beforehand
+pub fn check_date_format(format: &str) -> Result<String> {
+    match StrftimeItems::new(format).any(|item| item == Item::Error) {
+        true => Err(anyhow!(
+            "'{format}' isn't a valid date format, e.g: %d/%m/%Y"
+        )),
+        false => Ok(format.to_string()),
+    }
+}
+
+/// remove the keys of `value` that `known` doesn't have, the tables are walked recursively
+///
+/// the keymap is a map whose keys are the actions, every action is in the default one
+fn remove_unknown_keys(
+    value: &mut toml::Value,
+    known: &toml::Value,
+    prefix: &str,
+    unknown_keys: &mut Vec<String>,
+) {
+    let (Some(table), Some(known)) = (value.as_table_mut(), known.as_table()) else {
+        return;
+    };
+    let unknown = table
+        .keys()
+        .filter(|key| !known.contains_key(*key))
+        .cloned()
+        .collect::<Vec<_>>();
+    for key in unknown {
+        table.remove(&key);
+        unknown_keys.push(format!("{prefix}{key}"));
+    }
+    for (key, value) in table.iter_mut() {
+        remove_unknown_keys(value, &known[key], &format!("{prefix}{key}."), unknown_keys);
+    }
+}
+
+/// the paths of the config are relative to the home folder, `~` works too
+fn home_path(path: &str) -> PathBuf {
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    expand_path(path, &home)
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .map(|e| e.kind() == io::ErrorKind::NotFound)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod config_tests {
+    use std::path::Path;
+
+    use crossterm::event::KeyCode;
+    use tui::style::Color;
+
+    use crate::cli::args::BackendKind;
+
+    use super::{keymap::Action, Config, ConfirmPolicy, SizeUnits};
+
+    #[test]
+    fn test_parse_config() {
+        let (config, unknown_keys) = Config::parse(
+            r##"
+            exclude = ["*/vendor/*"]
+            protected = ["/srv/big"]
+            colour = "red"
+
+            [deletion]
+            backend = "cargo-clean"
+            confirm = "never"
+
+            [ui]
+            date-format = "%Y-%m-%d"
+            size-units = "decimal"
+            refresh = 50
+
+            [theme]
+            border = "#112233"
+
+            [keymap]
+            quit = "Q"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(unknown_keys, ["colour", "ui.refresh"]);
+        assert_eq!(config.deletion.backend, BackendKind::CargoClean);
+        assert_eq!(config.deletion.confirm, ConfirmPolicy::Never);
+        assert_eq!(config.deletion.grace_hours, 24);
+        assert_eq!(config.ui.size_units, SizeUnits::Decimal);
+        assert_eq!(config.ui.refresh_ms, 100);
+        assert_eq!(config.theme.border, Color::Rgb(0x11, 0x22, 0x33));
+        assert_eq!(config.keymap.action(KeyCode::Char('Q')), Some(Action::Quit));
+        assert!(config.is_protected(Path::new("/srv/big/a/target")));
+        assert!(!config.is_protected(Path::new("/srv/bigger/target")));
+
+        assert_eq!(Config::parse("").unwrap().0, Config::default());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Config::parse("exclude = \"*/vendor/*\"").is_err());
+        assert!(Config::parse("exclude = [\"[\"]").is_err());
+        assert!(Config::parse("roots = [\"/does/not/exist\"]").is_err());
+        assert!(Config::parse("[deletion]\nbackend = \"shred\"").is_err());
+        assert!(Config::parse("[ui]\ndate-format = \"%Q\"").is_err());
+        assert!(Config::parse("[theme]\nerror = \"purple\"").is_err());
+        assert!(Config::parse("[keymap]\nquit = \"m\"").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use serde::{de::Error, Deserialize, Deserializer};
+use toml::Table;
+
+use crate::app::predicate::parse_age;
+
+/// What a project asks of rtkill in its Cargo.toml, under `[package.metadata.rtkill]` or `[workspace.metadata.rtkill]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectMetadata {
+    /// its target is never deleted
+    pub protect: bool,
+    /// its target is only deleted once it wasn't built for this long, e.g: "30d"
+    #[serde(deserialize_with = "deserialize_age")]
+    pub max_age: Option<chrono::Duration>,
+    /// folders of its target left on the disk when it's deleted, e.g: ["release"]
+    pub keep: Vec<String>,
+}
+
+/// Why a target can't be deleted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Protection {
+    /// it's in one of the `protected` folders of the config, nothing overrides it
+    Config,
+    /// its Cargo.toml asks to keep it, `--ignore-protection` overrides it
+    Manifest(String),
+}
+
+impl Protection {
+    /// e.g: "its Cargo.toml protects it"
+    pub fn describe(&self) -> String {
+        match self {
+            Protection::Config => "in a folder protected by the config".to_string(),
+            Protection::Manifest(reason) => reason.clone(),
+        }
+    }
+
+    /// whether it still applies with `--ignore-protection` or without it
+    pub fn applies(&self, ignore_protection: bool) -> bool {
+        matches!(self, Protection::Config) || !ignore_protection
+    }
+}
+
+impl ProjectMetadata {
+    /// the `rtkill` table of the manifest, the package one first
+    ///
+    /// a table rtkill can't read protects the target, since what its owner wanted isn't known
+    pub fn from_manifest(manifest: &Table) -> Self {
+        Self::parse(manifest).unwrap_or_else(|_| Self {
+            protect: true,
+            ..Default::default()
+        })
+    }
+
+    /// read the manifest again, e.g: right before deleting its target
+    pub fn read(manifest_path: &Path) -> Result<Self> {
+        let manifest = fs::read_to_string(manifest_path)?.parse::<Table>()?;
+        Self::parse(&manifest)
+    }
+
+    pub fn parse(manifest: &Table) -> Result<Self> {
+        let table = ["package", "workspace"].iter().find_map(|section| {
+            manifest
+                .get(*section)?
+                .get("metadata")?
+                .get("rtkill")
+                .cloned()
+        });
+        let metadata = match table {
+            Some(table) => table
+                .try_into::<Self>()
+                .context("invalid [metadata.rtkill]")?,
+            None => return Ok(Self::default()),
+        };
+        // only the folders right inside the target, e.g: "release" or "doc"
+        if let Some(folder) = metadata
+            .keep
+            .iter()
+            .find(|folder| folder.is_empty() || folder.contains('/') || folder.starts_with('.'))
+        {
+            return Err(anyhow!(
+                "keep: '{folder}' isn't a folder of the target, e.g: release"
+            ));
+        }
+        Ok(metadata)
+    }
+
+    /// why its target can't be deleted, if it can't: e.g: "its Cargo.toml keeps it for 30 days after a build"
+    pub fn protection(
+        &self,
+        last_modified: DateTime<Local>,
+        now: DateTime<Local>,
+    ) -> Option<String> {
+        if self.protect {
+            return Some("its Cargo.toml protects it".to_string());
+        }
+        let max_age = self
+            .max_age
+            .filter(|max_age| now - last_modified < *max_age)?;
+        let age = match max_age.num_days() {
+            0 => format!("{} hours", max_age.num_hours()),
+            days => format!("{days} days"),
+        };
+        Some(format!("its Cargo.toml keeps it for {age} after a build"))
+    }
+}
+
+fn deserialize_age<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<chrono::Duration>, D::Error> {
+    let age = String::deserialize(deserializer)?;
+    parse_age(&age).map(Some).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod project_tests {
+    use chrono::{Duration, Local};
+    use toml::Table;
+
+    use super::ProjectMetadata;
+
+    fn parse(manifest: &str) -> anyhow::Result<ProjectMetadata> {
+        ProjectMetadata::parse(&manifest.parse::<Table>().unwrap())
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        assert_eq!(
+            parse("[package]\nname = \"a\"").unwrap(),
+            ProjectMetadata::default()
+        );
+        let metadata =
+            parse("[package.metadata.rtkill]\nmax-age = \"30d\"\nkeep = [\"release\"]").unwrap();
+        assert_eq!(metadata.max_age, Some(Duration::days(30)));
+        assert_eq!(metadata.keep, ["release"]);
+        assert!(
+            parse("[workspace]\nmembers = []\n[workspace.metadata.rtkill]\nprotect = true")
+                .unwrap()
+                .protect
+        );
+
+        assert!(parse("[package.metadata.rtkill]\nmax-age = \"soon\"").is_err());
+        assert!(parse("[package.metadata.rtkill]\nkeep = [\"../src\"]").is_err());
+        assert!(parse("[package.metadata.rtkill]\nprotected = true").is_err());
+        // what the owner wanted isn't known
+        let manifest = "[package.metadata.rtkill]\nprotect = \"yes\"".parse::<Table>();
+        assert!(ProjectMetadata::from_manifest(&manifest.unwrap()).protect);
+    }
+
+    #[test]
+    fn test_protection() {
+        let now = Local::now();
+        let metadata = ProjectMetadata {
+            max_age: Some(Duration::days(30)),
+            ..Default::default()
+        };
+        assert!(metadata.protection(now - Duration::days(3), now).is_some());
+        assert!(metadata.protection(now - Duration::days(40), now).is_none());
+        assert!(ProjectMetadata::default().protection(now, now).is_none());
+        let protected = ProjectMetadata {
+            protect: true,
+            ..Default::default()
+        };
+        assert!(protected
+            .protection(now - Duration::days(400), now)
+            .is_some());
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tui::style::Color;
+
+use crate::utils::FromHex;
+
+/// Colors of the app, a named terminal color (e.g: "lightred") or a hex one (e.g: "#e74c3c")
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Theme {
+    /// deleted targets, the message of what went well
+    #[serde(with = "color")]
+    pub success: Color,
+    #[serde(with = "color")]
+    pub info: Color,
+    /// busy and queued targets, outdated toolchains
+    #[serde(with = "color")]
+    pub warning: Color,
+    /// failed deletions
+    #[serde(with = "color")]
+    pub error: Color,
+    /// around the list and the popups
+    #[serde(with = "color")]
+    pub border: Color,
+    /// text of the highlighted row
+    #[serde(with = "color")]
+    pub selected_text: Color,
+    /// background of the highlighted row
+    #[serde(with = "color")]
+    pub selected: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            success: Color::Rgb(0x2e, 0xcc, 0x71),
+            info: Color::Rgb(0x34, 0x98, 0xdb),
+            warning: Color::Rgb(0xf1, 0xc9, 0x0f),
+            error: Color::Rgb(0xe7, 0x4c, 0x3c),
+            border: Color::LightRed,
+            selected_text: Color::Black,
+            selected: Color::White,
+        }
+    }
+}
+
+/// the terminal colors, by the name used in the config
+const NAMED_COLORS: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+pub fn parse_color(color: &str) -> Result<Color> {
+    let name = color.trim().to_lowercase().replace(['-', '_'], "");
+    match NAMED_COLORS.iter().find(|(n, _)| *n == name) {
+        Some((_, color)) => Ok(*color),
+        None if color.trim().starts_with('#') => Color::from_hex(color)
+            .map_err(|e| anyhow!("'{color}' isn't a hex color ({e}), e.g: #e74c3c")),
+        None => Err(anyhow!(
+            "unknown color '{color}', expected a hex color or one of {}",
+            NAMED_COLORS.map(|(n, _)| n).join(", ")
+        )),
+    }
+}
+
+pub fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        color => NAMED_COLORS
+            .iter()
+            .find(|(_, c)| *c == color)
+            .map(|(n, _)| n.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// serde of the colors, as their name
+mod color {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use tui::style::Color;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::color_name(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        super::parse_color(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod theme_tests {
+    use tui::style::Color;
+
+    use super::{color_name, parse_color};
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("LightRed").unwrap(), Color::LightRed);
+        assert_eq!(parse_color("dark-gray").unwrap(), Color::DarkGray);
+        assert_eq!(
+            parse_color("#e74c3c").unwrap(),
+            Color::Rgb(0xe7, 0x4c, 0x3c)
+        );
+        assert!(parse_color("#e74c").is_err());
+        assert!(parse_color("purple").is_err());
+        assert_eq!(color_name(Color::Rgb(0xe7, 0x4c, 0x3c)), "#e74c3c");
+        assert_eq!(color_name(Color::LightBlue), "lightblue");
+    }
+}
@@ -1,32 +1,79 @@
 mod app;
+mod artifacts;
+mod audit;
+mod backends;
+mod cli;
+mod config;
 mod ui;
 mod utils;
 
-use std::sync::Arc;
+use std::{process::ExitCode, sync::Arc};
 
 use anyhow::Result;
 use app::{run_app, AppState};
+use cli::args::{Cli, Command};
+use config::Config;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use tui::{backend::CrosstermBackend, Terminal};
-use utils::sharable_state::SharableState;
+use utils::{dry_run, sharable_state::SharableState};
+
+/// the config file, with the options of the command line overriding it
+fn load_config(cli: &Cli) -> Result<Config> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(units) = cli.size_units {
+        config.ui.size_units = units;
+    }
+    let tui_args = match &cli.command {
+        Some(Command::Tui(args)) => args,
+        _ => &cli.tui,
+    };
+    if let Some(format) = &tui_args.date_format {
+        config.ui.date_format = format.clone();
+    }
+    Ok(config)
+}
+
+fn main() -> Result<ExitCode> {
+    // invalid arguments exit here, with the error and the usage on stderr
+    let cli = Cli::parse_args();
+    if cli.dry_run {
+        dry_run::enable();
+    }
+    // `config check` reads the file itself, a broken config mustn't stop it
+    if !matches!(cli.command, Some(Command::Config(_))) {
+        config::init(load_config(&cli)?);
+    }
+
+    // non-interactive commands don't need the terminal ui
+    let tui_args = match cli.command {
+        None => cli.tui,
+        Some(Command::Tui(args)) => args,
+        Some(command) => {
+            let result = command.run();
+            // on stderr, so that it doesn't mix with what scripts parse
+            if dry_run::is_enabled() {
+                eprintln!("{}", dry_run::report());
+            }
+            return result;
+        }
+    };
+
+    // initial state, before the terminal is taken over so that errors are readable
+    let state = AppState::new(&tui_args)?;
+    let sharable_state = Arc::new(SharableState::new(state));
 
-fn main() -> Result<()> {
     enable_raw_mode()?;
     execute!(std::io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
 
     let backend = CrosstermBackend::new(std::io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
-    // initial state
-    let state = AppState::new().expect("Couldn't get your current directory, please provide it");
-    let sharable_state = Arc::new(SharableState::new(state));
-
     // app launch
-    let app_quit_result = run_app(&mut terminal, sharable_state);
+    let app_quit_result = run_app(&mut terminal, Arc::clone(&sharable_state));
 
     // app quit
     disable_raw_mode()?;
@@ -39,5 +86,11 @@ fn main() -> Result<()> {
     if let Err(e) = app_quit_result {
         eprintln!("{e}");
     }
-    Ok(())
+    if let Some(summary) = sharable_state.read().session.summary() {
+        println!("{summary}");
+    }
+    if dry_run::is_enabled() {
+        println!("{}", dry_run::report());
+    }
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,103 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::{app::analysis::Analysis, config, utils::bytes_len_to_string_prefix};
+
+use super::{list_with_state::visible_range, Renderer};
+
+impl Renderer<()> for Analysis {
+    /// takes a screen chunk and draw in it the savings summary, followed by the crates compiled in several targets
+    fn render_and_draw_items<B: Backend>(&self, f: &mut Frame<B>, chunks: Vec<Rect>) {
+        if chunks.len() < 4 {
+            return;
+        }
+        let report = &self.report;
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+
+        f.render_widget(
+            Paragraph::new(Spans::from(vec![
+                Span::raw(format!("{} targets hold ", report.targets)),
+                Span::styled(bytes_len_to_string_prefix(report.total_size), bold),
+                Span::raw(" of registry crates. A shared CARGO_TARGET_DIR would save "),
+                Span::styled(bytes_len_to_string_prefix(report.shared_savings), bold),
+                Span::raw(" (identical builds kept once)"),
+            ])),
+            chunks[0],
+        );
+        f.render_widget(
+            Paragraph::new(Spans::from(vec![
+                Span::raw("With sccache, "),
+                Span::styled(report.cache_hits.to_string(), bold),
+                Span::raw(
+                    " builds would be served from its cache instead of compiled again (disk usage unchanged) // Esc (Close)",
+                ),
+            ])),
+            chunks[1],
+        );
+
+        let rows = &chunks[3..];
+        if self.crates.datas.is_empty() {
+            f.render_widget(
+                Paragraph::new("No crate version is compiled in more than one target"),
+                rows[0],
+            );
+            return;
+        }
+
+        let items_range = visible_range(self.crates.index, self.crates.datas.len(), rows.len());
+        let items = self.crates.datas.iter().enumerate().collect::<Vec<_>>()[items_range].to_vec();
+        for (area, (item_id, item_data)) in rows.iter().zip(items) {
+            let item_block = if item_id == self.crates.index {
+                Block::default().style(
+                    Style::default()
+                        .fg(config::theme().selected_text)
+                        .bg(config::theme().selected),
+                )
+            } else {
+                Block::default()
+            };
+            f.render_widget(item_block, *area);
+
+            let sub_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(40), // 0: crate and version
+                    Constraint::Percentage(15), // 1: targets
+                    Constraint::Percentage(15), // 2: builds
+                    Constraint::Percentage(15), // 3: total size
+                    Constraint::Percentage(15), // 4: savings
+                ])
+                .split(*area);
+
+            f.render_widget(
+                Paragraph::new(format!("{} {}", item_data.name, item_data.version)),
+                sub_chunks[0],
+            );
+            f.render_widget(
+                Paragraph::new(format!("{} targets", item_data.targets)),
+                sub_chunks[1],
+            );
+            f.render_widget(
+                Paragraph::new(format!("{} builds", item_data.builds)),
+                sub_chunks[2],
+            );
+            f.render_widget(
+                Paragraph::new(bytes_len_to_string_prefix(item_data.total_size)),
+                sub_chunks[3],
+            );
+            f.render_widget(
+                Paragraph::new(format!(
+                    "-{} shared",
+                    bytes_len_to_string_prefix(item_data.shared_savings)
+                )),
+                sub_chunks[4],
+            );
+        }
+    }
+}
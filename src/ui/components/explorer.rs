@@ -0,0 +1,180 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::explorer::Explorer, artifacts::deps::DependencyUsage, config,
+    utils::bytes_len_to_string_prefix,
+};
+
+use super::{
+    list_with_state::{visible_range, ListWithState},
+    Renderer,
+};
+
+/// number of characters of the size bars
+const BAR_WIDTH: usize = 20;
+
+impl Renderer<()> for Explorer {
+    /// takes a screen chunk and draw in it the current folder path, followed by its children sorted by size
+    fn render_and_draw_items<B: Backend>(&self, f: &mut Frame<B>, chunks: Vec<Rect>) {
+        let (header, rows) = match chunks.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        if let Some(dependencies) = &self.dependencies {
+            f.render_widget(
+                Paragraph::new(Spans::from(vec![
+                    Span::styled(
+                        format!("Dependencies of {}", self.target_path),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" // c (Folders), Esc (Close)"),
+                ])),
+                *header,
+            );
+            return dependencies.render_and_draw_items(f, rows.to_vec());
+        }
+
+        let current_dir = self.current_dir();
+        f.render_widget(
+            Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    current_dir.path.to_string_lossy().to_string(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(
+                    " // {} // Enter (Open), Backspace (Up), Space (Delete), c (Dependencies), Esc (Close)",
+                    bytes_len_to_string_prefix(current_dir.size)
+                )),
+            ])),
+            *header,
+        );
+
+        if current_dir.children.is_empty() {
+            f.render_widget(Paragraph::new("(empty folder)"), rows[0]);
+            return;
+        }
+
+        let items_range = visible_range(self.index(), current_dir.children.len(), rows.len());
+        let items =
+            current_dir.children.iter().enumerate().collect::<Vec<_>>()[items_range].to_vec();
+        for (area, (item_id, item_data)) in rows.iter().zip(items) {
+            let item_block = if item_id == self.index() {
+                Block::default().style(
+                    Style::default()
+                        .fg(config::theme().selected_text)
+                        .bg(config::theme().selected),
+                )
+            } else {
+                Block::default()
+            };
+            f.render_widget(item_block, *area);
+
+            let sub_chunks = row_layout(*area);
+
+            draw_size_bar(f, &sub_chunks, item_data.size, current_dir.size);
+            f.render_widget(
+                Paragraph::new(match item_data.is_dir {
+                    true => format!("/{}", item_data.name),
+                    false => item_data.name.clone(),
+                }),
+                sub_chunks[3],
+            );
+        }
+    }
+}
+
+impl Renderer<()> for ListWithState<DependencyUsage> {
+    /// takes a screen chunk and draw in it the dependencies of a target, with how many variants of each were compiled
+    fn render_and_draw_items<B: Backend>(&self, f: &mut Frame<B>, chunks: Vec<Rect>) {
+        if self.datas.is_empty() {
+            if let Some(area) = chunks.first() {
+                f.render_widget(Paragraph::new("(no dependency artifacts)"), *area);
+            }
+            return;
+        }
+
+        let total = self.datas.iter().map(|d| d.size).sum::<u64>();
+        let items_range = visible_range(self.index, self.datas.len(), chunks.len());
+        let items = self.datas.iter().enumerate().collect::<Vec<_>>()[items_range].to_vec();
+        for (area, (item_id, item_data)) in chunks.iter().zip(items) {
+            let item_block = if item_id == self.index {
+                Block::default().style(
+                    Style::default()
+                        .fg(config::theme().selected_text)
+                        .bg(config::theme().selected),
+                )
+            } else {
+                Block::default()
+            };
+            f.render_widget(item_block, *area);
+
+            let sub_chunks = row_layout(*area);
+            draw_size_bar(f, &sub_chunks, item_data.size, total);
+            // several variants means the crate was rebuilt because of features, profiles or versions changes
+            let variants_style = match item_data.variants > 1 {
+                true => Style::default().add_modifier(Modifier::BOLD),
+                false => Style::default(),
+            };
+            f.render_widget(
+                Paragraph::new(Spans::from(vec![
+                    Span::raw(item_data.name.clone()),
+                    Span::styled(
+                        match item_data.variants {
+                            0 | 1 => String::new(),
+                            variants => format!(" ({variants} variants)"),
+                        },
+                        variants_style,
+                    ),
+                ])),
+                sub_chunks[3],
+            );
+        }
+    }
+}
+
+/// splits an explorer row into the size, percentage, bar and name columns
+fn row_layout(area: Rect) -> Vec<Rect> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(10),                   // 0: size
+            Constraint::Length(8),                    // 1: percentage
+            Constraint::Length(BAR_WIDTH as u16 + 3), // 2: bar
+            Constraint::Min(1),                       // 3: name
+        ])
+        .split(area)
+}
+
+/// draw the size, the percentage of `total` and the percentage bar of a row
+fn draw_size_bar<B: Backend>(f: &mut Frame<B>, sub_chunks: &[Rect], size: u64, total: u64) {
+    let ratio = match total {
+        0 => 0.0,
+        total => size as f64 / total as f64,
+    };
+    let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+
+    f.render_widget(
+        Paragraph::new(bytes_len_to_string_prefix(size)),
+        sub_chunks[0],
+    );
+    f.render_widget(
+        Paragraph::new(format!("{:5.1}%", ratio * 100.0)),
+        sub_chunks[1],
+    );
+    f.render_widget(
+        Paragraph::new(format!(
+            "[{}{}]",
+            "#".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled)
+        )),
+        sub_chunks[2],
+    );
+}
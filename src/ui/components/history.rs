@@ -0,0 +1,103 @@
+use chrono::DateTime;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::history::HistoryView, audit::AuditOutcome, config, utils::bytes_len_to_string_prefix,
+};
+
+use super::{list_with_state::visible_range, Renderer};
+
+impl Renderer<()> for HistoryView {
+    /// takes a screen chunk and draw in it the audit log, last record first
+    fn render_and_draw_items<B: Backend>(&self, f: &mut Frame<B>, chunks: Vec<Rect>) {
+        let (header, rows) = match chunks.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        f.render_widget(
+            Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    "Everything rtkill deleted, pruned or purged",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" // Esc (Close), `rtkill history` to query it"),
+            ])),
+            *header,
+        );
+
+        let records = &self.records;
+        if records.datas.is_empty() {
+            f.render_widget(Paragraph::new("The audit log is empty"), rows[0]);
+            return;
+        }
+
+        let items_range = visible_range(records.index, records.datas.len(), rows.len());
+        let items = records.datas.iter().enumerate().collect::<Vec<_>>()[items_range].to_vec();
+        for (area, (item_id, record)) in rows.iter().zip(items) {
+            let item_block = if item_id == records.index {
+                Block::default().style(
+                    Style::default()
+                        .fg(config::theme().selected_text)
+                        .bg(config::theme().selected),
+                )
+            } else {
+                Block::default()
+            };
+            f.render_widget(item_block, *area);
+
+            let sub_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(12), // 0: date
+                    Constraint::Percentage(8),  // 1: user
+                    Constraint::Percentage(14), // 2: action and backend
+                    Constraint::Percentage(8),  // 3: bytes freed
+                    Constraint::Percentage(8),  // 4: outcome
+                    Constraint::Percentage(50), // 5: path, and the error if any
+                ])
+                .split(*area);
+
+            let date = DateTime::parse_from_rfc3339(&record.timestamp)
+                .map(|date| {
+                    date.format(&format!("{} %H:%M", config::get().ui.date_format))
+                        .to_string()
+                })
+                .unwrap_or_else(|_| record.timestamp.clone());
+            f.render_widget(Paragraph::new(date), sub_chunks[0]);
+            f.render_widget(Paragraph::new(record.user.clone()), sub_chunks[1]);
+            f.render_widget(
+                Paragraph::new(format!("{} ({})", record.action.name(), record.backend)),
+                sub_chunks[2],
+            );
+            f.render_widget(
+                Paragraph::new(bytes_len_to_string_prefix(record.bytes_freed)),
+                sub_chunks[3],
+            );
+            f.render_widget(
+                Paragraph::new(Span::styled(
+                    record.outcome.name(),
+                    Style::default().fg(match record.outcome {
+                        AuditOutcome::Done => config::theme().success,
+                        AuditOutcome::Partial => config::theme().warning,
+                        AuditOutcome::Failed => config::theme().error,
+                    }),
+                )),
+                sub_chunks[4],
+            );
+            f.render_widget(
+                Paragraph::new(match &record.error {
+                    Some(error) => format!("{}: {error}", record.path),
+                    None => record.path.clone(),
+                }),
+                sub_chunks[5],
+            );
+        }
+    }
+}
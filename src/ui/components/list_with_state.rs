@@ -1,13 +1,19 @@
+use std::ops::Range;
+
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    text::Span,
+    style::{Modifier, Style},
+    text::{Span, Spans},
     widgets::{Block, Paragraph},
     Frame,
 };
 
-use crate::{app::TargetDir, utils::FromHex};
+use crate::{
+    app::TargetDir,
+    config,
+    utils::{bytes_len_to_string_prefix, dry_run},
+};
 
 use super::Renderer;
 
@@ -22,12 +28,18 @@ impl<T> ListWithState<T> {
         &self.datas[self.index]
     }
     pub fn next(&mut self) {
+        if self.datas.is_empty() {
+            return;
+        }
         match self.index >= self.datas.len() - 1 {
             true => self.index = 0,
             false => self.index += 1,
         }
     }
     pub fn previous(&mut self) {
+        if self.datas.is_empty() {
+            return;
+        }
         match self.index == 0 {
             true => self.index = self.datas.len() - 1,
             false => self.index -= 1,
@@ -35,6 +47,27 @@ impl<T> ListWithState<T> {
     }
 }
 
+/// all items cannot be displayed on screen thus this wil choose which items to display based on where is the currently selected one, it works by 'room': it search in which interval of "n" items the currently selected one is and display this interval
+pub fn visible_range(index: usize, len: usize, slots: usize) -> Range<usize> {
+    let (mut inf, mut sup) = (0, slots);
+    while !(inf <= index + 1 && index < sup) {
+        inf = sup;
+        sup += slots
+    }
+
+    if sup >= len {
+        sup = len
+    }
+
+    inf..sup
+}
+
+/// A row of the target list, the pinned targets have their own section
+enum ListRow<'a> {
+    Section(String),
+    Target(usize, &'a TargetDir),
+}
+
 impl Renderer<()> for ListWithState<TargetDir> {
     /// takes a screen chunk and draw in it the target items components
     fn render_and_draw_items<B: Backend>(&self, f: &mut Frame<B>, chunks: Vec<Rect>) {
@@ -42,69 +75,175 @@ impl Renderer<()> for ListWithState<TargetDir> {
             return;
         }
 
-        // all items cannot be displayed on screen thus this wil choose which items to display based on where is the currently selected one, it works by 'room': it search in which interval of "n" items the currently selected one is and display this interval
-        let items_range = {
-            let (mut inf, mut sup) = (0, chunks.len());
-            while !(inf <= self.index + 1 && self.index < sup) {
-                inf = sup;
-                sup += chunks.len()
+        // the pinned targets are first, a header starts their section and the one of the others
+        let pinned = self.pinned_count();
+        let mut rows = vec![];
+        for (item_id, item_data) in self.datas.iter().enumerate() {
+            match item_id {
+                0 if pinned > 0 => rows.push(ListRow::Section(format!("📌 Pinned ({pinned})"))),
+                _ if item_id == pinned && pinned > 0 => {
+                    rows.push(ListRow::Section("Others".to_string()))
+                }
+                _ => (),
             }
+            rows.push(ListRow::Target(item_id, item_data));
+        }
+        let selected_row = rows
+            .iter()
+            .position(|row| matches!(row, ListRow::Target(item_id, _) if *item_id == self.index))
+            .unwrap_or(0);
 
-            if sup >= self.datas.len() {
-                sup = self.datas.len()
-            }
-
-            inf..sup
-        };
-
-        // for each items, render it's component
-        let items = self.datas.iter().enumerate().collect::<Vec<_>>()[items_range].to_vec();
-        for (slot_id, area) in chunks.iter().enumerate() {
-            if slot_id >= items.len() {
-                break;
+        let rows_range = visible_range(selected_row, rows.len(), chunks.len());
+        for (row, area) in rows[rows_range].iter().zip(chunks) {
+            match row {
+                ListRow::Section(title) => f.render_widget(
+                    Paragraph::new(Span::styled(
+                        title.as_str(),
+                        Style::default()
+                            .fg(config::theme().info)
+                            .add_modifier(Modifier::BOLD),
+                    )),
+                    area,
+                ),
+                ListRow::Target(item_id, item_data) => {
+                    draw_target(f, area, item_data, *item_id == self.index)
+                }
             }
-            let (item_id, item_data) = &items[slot_id];
+        }
+    }
+}
 
-            let item_block = if item_id == &self.index {
-                Block::default().style(Style::default().fg(Color::Black).bg(Color::White))
-            } else {
-                Block::default()
-            };
-            f.render_widget(item_block, *area);
+/// one row of the list: the target's name and state, path, outdated artifacts, last build date and size
+fn draw_target<B: Backend>(f: &mut Frame<B>, area: Rect, item_data: &TargetDir, selected: bool) {
+    let item_block = if selected {
+        Block::default().style(
+            Style::default()
+                .fg(config::theme().selected_text)
+                .bg(config::theme().selected),
+        )
+    } else {
+        Block::default()
+    };
+    f.render_widget(item_block, area);
 
-            let sub_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(20), // 0: name
-                    Constraint::Percentage(1),  // separator
-                    Constraint::Percentage(65), // 2: path
-                    Constraint::Percentage(1),  // separator
-                    Constraint::Percentage(7),  // 4: last modified
-                    Constraint::Percentage(1),  // separator
-                    Constraint::Percentage(5),  // 6: size
-                ])
-                .split(*area);
+    let sub_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(20), // 0: name
+            Constraint::Percentage(1),  // separator
+            Constraint::Percentage(54), // 2: path
+            Constraint::Percentage(1),  // separator
+            Constraint::Percentage(10), // 4: outdated toolchains size
+            Constraint::Percentage(1),  // separator
+            Constraint::Percentage(7),  // 6: last modified
+            Constraint::Percentage(1),  // separator
+            Constraint::Percentage(5),  // 8: size
+        ])
+        .split(area);
 
-            // "[DELETED]" if user has deleted this target folder otherwise the project name
-            f.render_widget(
-                match item_data.is_deleted {
-                    true => Paragraph::new(Span::styled(
-                        "[DELETED]",
-                        Style::default()
-                            .fg(Color::from_hex("#e74c3c").unwrap())
-                            .add_modifier(Modifier::BOLD),
-                    )),
-                    false => Paragraph::new(item_data.project_name.clone()),
+    // "[DELETED]" if user has deleted this target folder ("[WOULD DELETE]" in a dry run), "[DELETING]" while it's in the deletion queue, otherwise the project name
+    f.render_widget(
+        match (item_data.is_deleted, item_data.deletion) {
+            (_, Some(progress)) => Paragraph::new(Span::styled(
+                match (progress.started, progress.waiting_for_lock) {
+                    (_, true) => "[WAITING FOR CARGO]",
+                    (true, false) => "[DELETING…]",
+                    (false, false) => "[QUEUED]",
                 },
-                sub_chunks[0],
-            );
-            f.render_widget(Paragraph::new(item_data.path.clone()), sub_chunks[2]); // target path
-            f.render_widget(
-                Paragraph::new(item_data.last_modified.clone()), // last modified
-                sub_chunks[4],
-            );
-            // target size
-            f.render_widget(Paragraph::new(item_data.size.clone()), sub_chunks[6]);
-        }
+                Style::default()
+                    .fg(config::theme().warning)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            (true, None) => Paragraph::new(Span::styled(
+                match dry_run::is_enabled() {
+                    true => "[WOULD DELETE]",
+                    false => "[DELETED]",
+                },
+                Style::default()
+                    .fg(config::theme().error)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            (false, None) => Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    format!("{}{}", item_data.mark_symbol(), item_data.project_name),
+                    match item_data.is_marked {
+                        true => Style::default().add_modifier(Modifier::BOLD),
+                        false => Style::default(),
+                    },
+                ),
+                // its Cargo.toml or the config protects it
+                Span::raw(match item_data.protection.is_some() {
+                    true => " 🔒",
+                    false => "",
+                }),
+                // a cargo process is building in it
+                Span::styled(
+                    match item_data.is_busy {
+                        true => " [BUSY]",
+                        false => "",
+                    },
+                    Style::default().fg(config::theme().warning),
+                ),
+                // its last deletion failed
+                Span::styled(
+                    match item_data.failed_deletion.is_some() {
+                        true => " [FAILED]",
+                        false => "",
+                    },
+                    Style::default().fg(config::theme().error),
+                ),
+            ])),
+        },
+        sub_chunks[0],
+    );
+    // target path, followed by what's left of it if its deletion failed
+    f.render_widget(
+        Paragraph::new(Spans::from(vec![
+            Span::raw(item_data.path.clone()),
+            Span::styled(
+                match (item_data.failure_summary(), item_data.deletion) {
+                    (Some(summary), None) => format!(" ({summary}, e to inspect)"),
+                    _ => String::new(),
+                },
+                Style::default().fg(config::theme().error),
+            ),
+        ])),
+        sub_chunks[2],
+    );
+    // deletion progress, or the artifacts built by compilers that aren't installed anymore
+    if let Some(progress) = item_data.deletion.filter(|p| p.started) {
+        f.render_widget(
+            Paragraph::new(format!(
+                "{}% ({} files)",
+                progress.bytes * 100 / item_data.size_bytes.max(1),
+                progress.files
+            )),
+            sub_chunks[4],
+        );
+    } else if item_data.outdated_toolchains_size > 0 && !item_data.is_deleted {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                format!(
+                    "{} outdated",
+                    bytes_len_to_string_prefix(item_data.outdated_toolchains_size)
+                ),
+                Style::default().fg(config::theme().warning),
+            )),
+            sub_chunks[4],
+        );
     }
+    f.render_widget(
+        Paragraph::new(
+            item_data
+                .last_modified
+                .format(&config::get().ui.date_format)
+                .to_string(),
+        ), // last modified
+        sub_chunks[6],
+    );
+    // target size
+    f.render_widget(
+        Paragraph::new(bytes_len_to_string_prefix(item_data.size_bytes)),
+        sub_chunks[8],
+    );
 }
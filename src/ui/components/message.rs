@@ -1,11 +1,11 @@
 use std::time::{Duration, Instant};
 
 use tui::{
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::Paragraph,
 };
 
-use crate::utils::FromHex;
+use crate::config;
 
 use super::Renderer;
 
@@ -18,6 +18,8 @@ pub enum MessageType {
 
 /// Action to take when the message is deleted
 pub enum MessageAction {
+    /// unused since invalid arguments are reported by the cli before the app starts
+    #[allow(dead_code)]
     Quit,
 }
 
@@ -54,10 +56,10 @@ impl Message {
 impl Renderer<Paragraph<'static>> for Message {
     fn render_items(&self) -> Option<Paragraph<'static>> {
         let color = match self.msg_type {
-            MessageType::Success => Color::from_hex("#2ecc71").unwrap(),
-            MessageType::Info => Color::from_hex("#3498db").unwrap(),
-            MessageType::Warning => Color::from_hex("#f1c90f").unwrap(),
-            MessageType::Error => Color::from_hex("#e74c3c").unwrap(),
+            MessageType::Success => config::theme().success,
+            MessageType::Info => config::theme().info,
+            MessageType::Warning => config::theme().warning,
+            MessageType::Error => config::theme().error,
         };
         Some(
             Paragraph::new(self.msg_text.clone())
@@ -1,9 +1,15 @@
 use tui::{backend::Backend, layout::Rect, Frame};
 
+pub mod analysis;
+pub mod explorer;
+pub mod history;
 pub mod list_with_state;
 pub mod logo;
 pub mod message;
+pub mod popup;
 pub mod rainbow_text;
+pub mod restore;
+pub mod selection_prompt;
 
 /// simple trait to origanize how component renders their ui
 pub trait Renderer<T> {
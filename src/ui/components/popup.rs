@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+use tui::{
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+};
+
+use crate::{
+    app::budget::Budget,
+    artifacts::{
+        dedupe::{DedupePlan, LinkKind},
+        prune::PrunePlan,
+    },
+    config,
+};
+
+use super::Renderer;
+
+/// Action to run when the user confirms the popup
+pub enum PopupAction {
+    Prune(PrunePlan),
+    /// replace the duplicated artifacts of these targets by links
+    Dedupe {
+        plan: DedupePlan,
+        targets: usize,
+        link: LinkKind,
+    },
+    /// delete the marked target dirs
+    DeleteTargets(Vec<String>),
+    /// delete the target dirs planned to meet the budget, in this order, until it's met
+    FreeSpace(Budget, Vec<String>),
+    /// delete the file or folder selected in the explorer, if it's still this one
+    DeleteExplorerSelection(PathBuf),
+    /// unpin the project of this manifest
+    Unpin(String),
+}
+
+/// Describe a confirmation popup, drawn on top of the app until the user answers it
+pub struct Popup {
+    title: String,
+    lines: Vec<String>,
+    pub action: PopupAction,
+}
+
+impl Popup {
+    pub fn new(title: &str, lines: Vec<String>, action: PopupAction) -> Self {
+        Self {
+            title: title.to_string(),
+            lines,
+            action,
+        }
+    }
+}
+
+impl Renderer<Paragraph<'static>> for Popup {
+    fn render_items(&self) -> Option<Paragraph<'static>> {
+        let mut text = self
+            .lines
+            .iter()
+            .map(|line| Spans::from(line.clone()))
+            .collect::<Vec<_>>();
+        text.push(Spans::default());
+        text.push(Spans::from(Span::styled(
+            match self.action {
+                PopupAction::Dedupe {
+                    link: LinkKind::Reflink,
+                    ..
+                } => "y (Confirm) // h (Use hardlinks) // n (Cancel)",
+                PopupAction::Dedupe { .. } => "y (Confirm) // h (Use reflinks) // n (Cancel)",
+                _ => "y (Confirm) // n (Cancel)",
+            },
+            Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        )));
+
+        Some(
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .title(self.title.clone())
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(config::theme().border)),
+                )
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+        )
+    }
+}
+
+/// returns a rect centered in `area`, taking `percent_x`% of its width and `percent_y`% of its height
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
@@ -0,0 +1,85 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::restore::{format_remaining, Restorable, RestoreView},
+    config,
+};
+
+use super::{list_with_state::visible_range, Renderer};
+
+impl Renderer<()> for RestoreView {
+    /// takes a screen chunk and draw in it the quarantined targets, followed by the trashed ones
+    fn render_and_draw_items<B: Backend>(&self, f: &mut Frame<B>, chunks: Vec<Rect>) {
+        let (header, rows) = match chunks.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        f.render_widget(
+            Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    "Targets moved to the quarantine or to the trash by rtkill",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" // Enter (Restore), p (Purge now), Esc (Close)"),
+            ])),
+            *header,
+        );
+
+        let entries = &self.entries;
+        if entries.datas.is_empty() {
+            f.render_widget(
+                Paragraph::new("The quarantine and the trash hold no target from rtkill"),
+                rows[0],
+            );
+            return;
+        }
+
+        let items_range = visible_range(entries.index, entries.datas.len(), rows.len());
+        let items = entries.datas.iter().enumerate().collect::<Vec<_>>()[items_range].to_vec();
+        for (area, (item_id, item_data)) in rows.iter().zip(items) {
+            let item_block = if item_id == entries.index {
+                Block::default().style(
+                    Style::default()
+                        .fg(config::theme().selected_text)
+                        .bg(config::theme().selected),
+                )
+            } else {
+                Block::default()
+            };
+            f.render_widget(item_block, *area);
+
+            let sub_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(12), // 0: quarantine or trash
+                    Constraint::Percentage(63), // 1: original path
+                    Constraint::Percentage(25), // 2: deletion date, or purge countdown
+                ])
+                .split(*area);
+
+            let (kind, when) = match item_data {
+                Restorable::Quarantined(entry) => (
+                    "[QUARANTINE]",
+                    match entry.purge_in().is_zero() {
+                        true => "purge due".to_string(),
+                        false => format!("purged in {}", format_remaining(entry.purge_in())),
+                    },
+                ),
+                Restorable::Trashed(entry) => ("[TRASH]", entry.deletion_date.replace('T', " ")),
+            };
+            f.render_widget(Paragraph::new(kind), sub_chunks[0]);
+            f.render_widget(
+                Paragraph::new(item_data.original_path().to_string_lossy().to_string()),
+                sub_chunks[1],
+            );
+            f.render_widget(Paragraph::new(when), sub_chunks[2]);
+        }
+    }
+}
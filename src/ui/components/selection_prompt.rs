@@ -0,0 +1,86 @@
+use tui::{
+    layout::Alignment,
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+};
+
+use crate::{
+    app::predicate::{PromptMode, SelectionPrompt, PREDICATES_SYNTAX},
+    config,
+    utils::bytes_len_to_string_prefix,
+};
+
+use super::Renderer;
+
+/// shown under the prompt input in the budget mode
+const BUDGET_SYNTAX: &str = "50G (free 50 GiB)  20% (until 20% of the disk is free)";
+
+impl Renderer<Paragraph<'static>> for SelectionPrompt {
+    fn render_items(&self) -> Option<Paragraph<'static>> {
+        let (title, syntax, confirm) = match self.mode {
+            PromptMode::Criteria => (
+                "Mark by criteria".to_string(),
+                PREDICATES_SYNTAX.to_string(),
+                "Enter (Mark the matches) // Esc (Cancel)",
+            ),
+            PromptMode::Budget(policy) => (
+                format!("Free space, {}", policy.describe()),
+                BUDGET_SYNTAX.to_string(),
+                "Enter (Mark them) // Tab (Switch the policy) // Esc (Cancel)",
+            ),
+        };
+        let preview = match &self.preview {
+            Ok((count, size)) => Span::styled(
+                match self.mode {
+                    PromptMode::Criteria => format!(
+                        "{count} targets match ({})",
+                        bytes_len_to_string_prefix(*size)
+                    ),
+                    PromptMode::Budget(_) => format!(
+                        "{count} targets ({}) would be deleted",
+                        bytes_len_to_string_prefix(*size)
+                    ),
+                },
+                Style::default()
+                    .fg(config::theme().success)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Err(why) => Span::styled(why.clone(), Style::default().fg(config::theme().error)),
+        };
+        let text = vec![
+            Spans::from(vec![
+                Span::styled("> ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(self.input.clone()),
+                Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]),
+            Spans::default(),
+            Spans::from(preview),
+            Spans::from(Span::styled(
+                self.warning.clone().unwrap_or_default(),
+                Style::default().fg(config::theme().warning),
+            )),
+            Spans::from(Span::styled(
+                syntax,
+                Style::default().add_modifier(Modifier::ITALIC),
+            )),
+            Spans::from(Span::styled(
+                confirm,
+                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            )),
+        ];
+
+        Some(
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(config::theme().info)),
+                )
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+        )
+    }
+}
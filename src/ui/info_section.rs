@@ -3,25 +3,29 @@ use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Span, Spans},
-    widgets::Paragraph,
+    widgets::{Paragraph, Wrap},
     Frame,
 };
 
-use crate::app::AppState;
+use crate::{
+    app::AppState,
+    config::{self, keymap::Action},
+    utils::bytes_len_to_string_prefix,
+};
 
 use super::components::{logo::welcome_logo, Renderer};
 
 /// draw the ui for the inner top section of the app
 pub fn draw_info_section<B: Backend>(f: &mut Frame<B>, area: Rect, state: &AppState) {
-    // divide the space in two chuncks of 80% (for the logo) and 20% (app infos)
+    // divide the space in two chuncks, the logo height (7 lines) and the remaining space for the app infos
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
-        .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+        .constraints([Constraint::Length(7), Constraint::Min(2)])
         .split(area);
     f.render_widget(welcome_logo().alignment(Alignment::Center), chunks[0]); // render logo
 
-    // redivide the 20% for the app infos, in two equal sub-chunks for the message/app info and the controls
+    // redivide the remaining space for the app infos, in two equal sub-chunks for the message/app info and the controls
     let sub_chunck = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
@@ -37,66 +41,155 @@ pub fn draw_info_section<B: Backend>(f: &mut Frame<B>, area: Rect, state: &AppSt
         f.render_widget(
             Paragraph::new(format!(
                 "Searching 'target' directories ⏳ Search scope: {}",
-                state.root_dir.clone().unwrap_or("undefined".to_string())
+                state.root_dirs.join(", ")
             ))
             .alignment(Alignment::Center),
             sub_chunck[0],
         );
     } else {
-        f.render_widget(
-            Paragraph::new(Spans::from(vec![
-                Span::raw("Found "),
+        let outdated_size = state
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| !t.is_deleted)
+            .map(|t| t.outdated_toolchains_size)
+            .sum::<u64>();
+
+        let mut summary = vec![
+            Span::raw("Found "),
+            Span::styled(
+                state.target_directories.datas.len().to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" 'target' directories"),
+            Span::raw(" // "),
+            Span::raw("Total size: "),
+            Span::styled(
+                bytes_len_to_string_prefix(state.total_size),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ];
+        let marked = state
+            .target_directories
+            .datas
+            .iter()
+            .filter(|t| t.is_marked && !t.is_deleted)
+            .collect::<Vec<_>>();
+        if !marked.is_empty() {
+            summary.extend([
+                Span::raw(" // "),
                 Span::styled(
-                    state.target_directories.datas.len().to_string(),
+                    format!(
+                        "{} marked ({})",
+                        marked.len(),
+                        bytes_len_to_string_prefix(marked.iter().map(|t| t.size_bytes).sum())
+                    ),
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
-                Span::raw(" 'target' directories"),
+            ]);
+        }
+        let freed = state.session.freed();
+        if freed > 0 {
+            summary.extend([
                 Span::raw(" // "),
-                Span::raw("Total size: "),
+                Span::raw("Freed this session: "),
                 Span::styled(
-                    state.total_size.clone(),
+                    bytes_len_to_string_prefix(freed),
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
-            ]))
-            .alignment(Alignment::Center),
+            ]);
+        }
+        if let Some(budget) = &state.budget {
+            summary.extend([
+                Span::raw(" // "),
+                Span::raw("Budget: "),
+                Span::styled(
+                    budget.status(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ]);
+        }
+        if outdated_size > 0 {
+            summary.extend([
+                Span::raw(" // "),
+                Span::styled(
+                    bytes_len_to_string_prefix(outdated_size),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" built by rustc versions you no longer have"),
+            ]);
+        }
+
+        f.render_widget(
+            Paragraph::new(Spans::from(summary))
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
             sub_chunck[0],
         );
     }
 
-    // render controls
-    f.render_widget(
-        Paragraph::new(Spans::from(vec![
-            Span::styled(
-                "Movement: ",
-                Style::default().add_modifier(Modifier::ITALIC),
-            ),
-            Span::styled(
-                "Up/Down",
-                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
-            ),
-            Span::raw(" // "),
-            Span::styled("Action: ", Style::default().add_modifier(Modifier::ITALIC)),
-            Span::styled(
-                "Space (Delete)",
-                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+    // render controls, with the keys of the config
+    let keymap = &config::get().keymap;
+    let controls = [
+        (
+            Action::Mark,
+            match state.instant_delete {
+                true => "Delete".to_string(),
+                false => "Mark".to_string(),
+            },
+        ),
+        (Action::MarkAll, "Mark all".to_string()),
+        (Action::InvertMarks, "Invert marks".to_string()),
+        (Action::Pin, "Pin".to_string()),
+        (Action::MarkByCriteria, "Mark by criteria".to_string()),
+        (Action::FreeSpace, "Free space".to_string()),
+        (Action::DeleteMarked, "Delete marked".to_string()),
+        (Action::DeletionErrors, "Deletion errors".to_string()),
+        (
+            Action::Backend,
+            format!("Backend: {}", state.backend.name()),
+        ),
+        (Action::Restore, "Restore".to_string()),
+        (Action::History, "History".to_string()),
+        (Action::Undo, "Undo".to_string()),
+        (Action::Quit, "Quit".to_string()),
+        (Action::Refresh, "Refresh".to_string()),
+        (Action::Explore, "Explore".to_string()),
+        (Action::Open, "open".to_string()),
+        (Action::Prune, "Prune".to_string()),
+        (Action::PruneToolchains, "Prune old toolchains".to_string()),
+        (Action::Analysis, "Duplicates analysis".to_string()),
+        (Action::Dedupe, "Dedupe".to_string()),
+    ];
+    let mut spans = vec![
+        Span::styled(
+            "Movement: ",
+            Style::default().add_modifier(Modifier::ITALIC),
+        ),
+        Span::styled(
+            format!(
+                "{}/{}",
+                keymap.key_name(Action::Up),
+                keymap.key_name(Action::Down)
             ),
-            Span::raw(", "),
-            Span::styled(
-                "q (Quit)",
-                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
-            ),
-            Span::raw(", "),
-            Span::styled(
-                "r (Refresh)",
-                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
-            ),
-            Span::raw(", "),
-            Span::styled(
-                "o (open)",
-                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
-            ),
-        ]))
-        .alignment(Alignment::Center),
+            Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ),
+        Span::raw(" // "),
+        Span::styled("Action: ", Style::default().add_modifier(Modifier::ITALIC)),
+    ];
+    for (i, (action, label)) in controls.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(", "));
+        }
+        spans.push(Span::styled(
+            format!("{} ({label})", keymap.key_name(action)),
+            Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ));
+    }
+    f.render_widget(
+        Paragraph::new(Spans::from(spans))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
         sub_chunck[1],
     );
 }
@@ -8,12 +8,23 @@ use crate::app::AppState;
 
 use super::components::Renderer;
 
-/// draw ui for the list of target dirs
+/// draw ui for the list of target dirs, or for the explorer, the analysis, the restore view or the history when one of them is opened
 pub fn draw_list_section<B: Backend>(f: &mut Frame<B>, area: Rect, state: &AppState) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([Constraint::Min(1)].repeat(33))
         .split(area);
-    state.target_directories.render_and_draw_items(f, chunks)
+    match (
+        &state.explorer,
+        &state.analysis,
+        &state.restore,
+        &state.history,
+    ) {
+        (Some(explorer), _, _, _) => explorer.render_and_draw_items(f, chunks),
+        (None, Some(analysis), _, _) => analysis.render_and_draw_items(f, chunks),
+        (None, None, Some(restore), _) => restore.render_and_draw_items(f, chunks),
+        (None, None, None, Some(history)) => history.render_and_draw_items(f, chunks),
+        (None, None, None, None) => state.target_directories.render_and_draw_items(f, chunks),
+    }
 }
@@ -3,15 +3,17 @@ mod info_section;
 mod list_section;
 
 use self::{
-    components::rainbow_text::rainbow_text, info_section::draw_info_section,
+    components::{popup::centered_rect, rainbow_text::rainbow_text, Renderer},
+    info_section::draw_info_section,
     list_section::draw_list_section,
 };
-use crate::app::AppState;
+use crate::{app::AppState, config, utils::dry_run};
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, BorderType, Borders},
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, BorderType, Borders, Clear},
     Frame,
 };
 
@@ -22,18 +24,54 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, state: &AppState) {
         .constraints([Constraint::Percentage(25), Constraint::Percentage(75)].as_ref())
         .split(f.size());
 
+    let mut title = rainbow_text("RTKILL::Rust Target Killer");
+    if dry_run::is_enabled() {
+        title.0.push(Span::raw(" "));
+        title.0.push(Span::styled(
+            " DRY RUN: nothing is deleted ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
     let info_section = Block::default()
-        .title(rainbow_text("RTKILL::Rust Target Killer"))
+        .title(title)
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
     f.render_widget(info_section, parent_chunk[0]);
     draw_info_section(f, parent_chunk[0], state);
 
     let list_section = Block::default()
-        .title(rainbow_text("Target directories"))
+        .title(rainbow_text(
+            match (
+                &state.explorer,
+                &state.analysis,
+                &state.restore,
+                &state.history,
+            ) {
+                (Some(_), _, _, _) => "Explorer",
+                (None, Some(_), _, _) => "Duplicated dependencies",
+                (None, None, Some(_), _) => "Quarantine and trash",
+                (None, None, None, Some(_)) => "History",
+                (None, None, None, None) => "Target directories",
+            },
+        ))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::LightRed));
+        .border_style(Style::default().fg(config::theme().border));
     f.render_widget(list_section, parent_chunk[1]);
     draw_list_section(f, parent_chunk[1], state);
+
+    // popups are drawn last, on top of everything else
+    if let Some(popup) = &state.popup {
+        let area = centered_rect(60, 30, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(popup.render_items().unwrap(), area);
+    }
+    if let Some(prompt) = &state.selection_prompt {
+        let area = centered_rect(60, 30, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(prompt.render_items().unwrap(), area);
+    }
 }
@@ -0,0 +1,52 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    mem::MaybeUninit,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+/// Space of the filesystem holding a path, as reported by `statvfs`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// device id of the filesystem, to tell apart the targets living on different disks
+    pub device: u64,
+    /// bytes available to unprivileged users
+    pub free: u64,
+    pub total: u64,
+}
+
+impl DiskSpace {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let device = fs::metadata(path)?.dev();
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: `c_path` is a valid C string, and `stat` is only read once `statvfs` filled it
+        let stat = unsafe {
+            if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            stat.assume_init()
+        };
+        Ok(Self {
+            device,
+            free: stat.f_bavail as u64 * stat.f_frsize as u64,
+            total: stat.f_blocks as u64 * stat.f_frsize as u64,
+        })
+    }
+}
+
+/// the highest parent of `path` on the same filesystem
+pub fn mount_point(path: &Path) -> Result<PathBuf> {
+    let device = fs::metadata(path)?.dev();
+    let mut top = path;
+    while let Some(parent) = top.parent() {
+        if fs::metadata(parent)?.dev() != device {
+            break;
+        }
+        top = parent;
+    }
+    Ok(top.to_path_buf())
+}
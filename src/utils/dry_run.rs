@@ -0,0 +1,47 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use lazy_static::lazy_static;
+
+/// nothing is deleted, pruned or linked, every destructive operation only records what it would have done
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref RECORDED: Mutex<Vec<String>> = Mutex::new(vec![]);
+}
+
+/// enabled once at startup, for the whole session
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// remember an operation that was skipped, e.g: "/a/target would be deleted permanently"
+pub fn record(action: String) {
+    if let Ok(mut recorded) = RECORDED.lock() {
+        recorded.push(action)
+    }
+}
+
+/// what would have happened during the session, printed once rtkill exits
+pub fn report() -> String {
+    let recorded = RECORDED.lock().map(|r| r.clone()).unwrap_or_default();
+    if recorded.is_empty() {
+        return "Dry run: nothing would have been changed".to_string();
+    }
+
+    let mut report = format!(
+        "Dry run: nothing was changed, {} operations were skipped:",
+        recorded.len()
+    );
+    for action in recorded {
+        report.push_str("\n  ");
+        report.push_str(&action);
+    }
+    report
+}
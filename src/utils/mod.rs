@@ -1,9 +1,15 @@
+use std::{env, path::PathBuf};
+
 use anyhow::{anyhow, Result};
 use number_prefix::NumberPrefix;
 use rand::{thread_rng, Rng};
 use tui::style::Color;
+
+use crate::config::{self, SizeUnits};
 mod tests;
 
+pub mod disk;
+pub mod dry_run;
 pub mod sharable_state;
 
 /// generate a random **light** color
@@ -22,13 +28,82 @@ pub fn generate_random_color() -> Color {
 /// ```
 /// assert_eq!(bytes_len_to_string_prefix(1_073_741_824), "1 GiB")
 /// ```
+///
+/// or "1.1 GB" with the decimal units of the config
 pub fn bytes_len_to_string_prefix(bin_size: u64) -> String {
-    match NumberPrefix::binary(bin_size as f64) {
+    let prefixed = match config::get().ui.size_units {
+        SizeUnits::Binary => NumberPrefix::binary(bin_size as f64),
+        SizeUnits::Decimal => NumberPrefix::decimal(bin_size as f64),
+    };
+    match prefixed {
         NumberPrefix::Standalone(bytes) => format!("{bytes}B"),
         NumberPrefix::Prefixed(prefix, n) => format!("{:.1} {}B", n, prefix),
     }
 }
 
+/// inverse of [bytes_len_to_string_prefix], e.g: "2G", "2 GiB", "1.5gb", "500M" or "4096" (bytes)
+///
+/// "GiB" is always 1024³ bytes, "G" and "GB" follow the units of the config, like the sizes shown
+pub fn parse_bytes(size: &str) -> Result<u64> {
+    parse_bytes_in(size, config::get().ui.size_units)
+}
+
+/// the sizes of the command line are parsed before the config is read, they're only checked then
+pub fn check_size(size: &str) -> Result<String> {
+    parse_bytes_in(size, SizeUnits::Binary).map(|_| size.to_string())
+}
+
+fn parse_bytes_in(size: &str, units: SizeUnits) -> Result<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| anyhow!("'{size}' isn't a size, e.g: 2G or 500M"))?;
+    let unit = unit.trim().to_lowercase();
+    let unit = unit.trim_end_matches('b');
+    let base = match (unit.strip_suffix('i'), units) {
+        (Some(_), _) | (None, SizeUnits::Binary) => 1024_f64,
+        (None, SizeUnits::Decimal) => 1000_f64,
+    };
+    let power = match unit.trim_end_matches('i') {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return Err(anyhow!("unknown unit in '{size}', expected K, M, G or T")),
+    };
+    Ok((number * base.powi(power)) as u64)
+}
+
+/// `$XDG_STATE_HOME/rtkill` (`~/.local/state/rtkill` by default), where rtkill keeps what it has to remember between sessions
+pub fn state_dir() -> Result<PathBuf> {
+    Ok(xdg_home("XDG_STATE_HOME", ".local/state")?.join("rtkill"))
+}
+
+/// `$XDG_CONFIG_HOME/rtkill` (`~/.config/rtkill` by default), where the user configures rtkill
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(xdg_home("XDG_CONFIG_HOME", ".config")?.join("rtkill"))
+}
+
+/// `$XDG_DATA_HOME`, `~/.local/share` by default
+pub fn data_home() -> Result<PathBuf> {
+    xdg_home("XDG_DATA_HOME", ".local/share")
+}
+
+/// the spec requires to ignore the relative paths
+fn xdg_home(var: &str, default: &str) -> Result<PathBuf> {
+    match env::var_os(var).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Ok(path),
+        _ => {
+            Ok(PathBuf::from(env::var_os("HOME").ok_or(anyhow!("$HOME isn't set"))?).join(default))
+        }
+    }
+}
+
 /* Only when developping, because tuirs takes ownership of the terminal screen, I can't log anything when debuging, so I write the debug content in a file
 pub fn log_print(log: String) {
     use std::{
@@ -1,11 +1,17 @@
 #[cfg(test)]
 mod utils_tests {
-    use std::{mem::ManuallyDrop, sync::Arc, thread};
+    use std::{sync::Arc, thread};
 
     use rand::{thread_rng, Rng};
     use tui::style::Color;
 
-    use crate::utils::{bytes_len_to_string_prefix, sharable_state::SharableState, FromHex};
+    use crate::{
+        config::SizeUnits,
+        utils::{
+            bytes_len_to_string_prefix, parse_bytes, parse_bytes_in, sharable_state::SharableState,
+            FromHex,
+        },
+    };
 
     #[test]
     fn test_format_size() {
@@ -15,6 +21,24 @@ mod utils_tests {
         assert_eq!(bytes_len_to_string_prefix(2_u64.pow(30)), "1.0 GiB");
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_bytes("4096").unwrap(), 4096);
+        assert_eq!(parse_bytes("2G").unwrap(), 2 * 2_u64.pow(30));
+        assert_eq!(parse_bytes("2 GiB").unwrap(), 2 * 2_u64.pow(30));
+        assert_eq!(parse_bytes("1.5mb").unwrap(), 3 * 2_u64.pow(19));
+        assert!(parse_bytes("G").is_err());
+        assert_eq!(
+            parse_bytes_in("2GB", SizeUnits::Decimal).unwrap(),
+            2_000_000_000
+        );
+        assert_eq!(
+            parse_bytes_in("2 GiB", SizeUnits::Decimal).unwrap(),
+            2 * 2_u64.pow(30)
+        );
+        assert!(parse_bytes("2 parsecs").is_err());
+    }
+
     #[test]
     fn test_from_hex() {
         // tests colors in app
@@ -56,7 +80,7 @@ mod utils_tests {
         });
 
         loop {
-            let data = ManuallyDrop::<Vec<usize>>::into_inner(basic_state.read().clone());
+            let data = basic_state.read().clone();
             if data.len() == 1000 {
                 assert_eq!(data, (0..1000).collect::<Vec<_>>());
                 break;
@@ -0,0 +1,70 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const HASH: &str = "0123456789abcdef";
+
+/// a project whose target holds one unit, its fingerprint and its rlib
+fn project(root: &Path, name: &str) -> PathBuf {
+    let project = root.join(name);
+    let profile = project.join("target/debug");
+    fs::create_dir_all(profile.join(format!(".fingerprint/{name}-{HASH}"))).unwrap();
+    fs::create_dir_all(profile.join("deps")).unwrap();
+    fs::write(
+        project.join("Cargo.toml"),
+        format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n"),
+    )
+    .unwrap();
+    fs::write(
+        profile.join(format!(".fingerprint/{name}-{HASH}/lib-{name}")),
+        HASH,
+    )
+    .unwrap();
+    fs::write(
+        profile.join(format!("deps/lib{name}-{HASH}.rlib")),
+        [0; 4096],
+    )
+    .unwrap();
+    project
+}
+
+#[test]
+fn test_pinned_target_survives_prune() {
+    let root = std::env::temp_dir().join(format!("rtkill-prune-pins-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let (workspace, state) = (root.join("workspace"), root.join("state"));
+    let pinned = project(&workspace, "pinned");
+    let other = project(&workspace, "other");
+    fs::create_dir_all(state.join("rtkill")).unwrap();
+    fs::write(
+        state.join("rtkill/pins"),
+        format!(
+            "{}\n",
+            pinned.join("Cargo.toml").canonicalize().unwrap().display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rtkill"))
+        .args(["prune", "--days", "0", "--yes"])
+        .arg(&workspace)
+        .env("XDG_STATE_HOME", &state)
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("XDG_DATA_HOME", root.join("data"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    assert!(pinned
+        .join(format!("target/debug/deps/libpinned-{HASH}.rlib"))
+        .exists());
+    assert!(pinned
+        .join(format!("target/debug/.fingerprint/pinned-{HASH}"))
+        .exists());
+    assert!(!other
+        .join(format!("target/debug/deps/libother-{HASH}.rlib"))
+        .exists());
+    fs::remove_dir_all(root).unwrap();
+}